@@ -1,4 +1,4 @@
-use crate::{get_state_with_topo_id, set_state_with_topo_id, update_state_with_topo_id, use_state};
+use crate::{get_state_with_topo_id, set_state_with_topo_id, update_state_with_topo_id};
 use std::marker::PhantomData;
 
 #[derive(Clone, Default)]
@@ -51,10 +51,17 @@ where
     }
 }
 
+// The mailbox is stored against the component's own topo::Id rather than a hook slot
+// because senders address it by that id.
 pub fn use_mailbox<T: Clone>() -> (Mailbox<T>, MailboxControl<T>) {
-    let (mailbox, _mailbox_access) = use_state(Mailbox::<T>::new);
+    let current_id = topo::Id::current();
+    let mailbox = get_state_with_topo_id::<Mailbox<T>>(current_id).unwrap_or_else(|| {
+        let mailbox = Mailbox::<T>::new();
+        set_state_with_topo_id(mailbox.clone(), current_id);
+        mailbox
+    });
 
-    let ctrl = MailboxControl::new(topo::Id::current());
+    let ctrl = MailboxControl::new(current_id);
 
     (mailbox, ctrl)
 }
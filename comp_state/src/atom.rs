@@ -0,0 +1,83 @@
+// App wide state that is not tied to the component topology.
+// An atom's slot is keyed by where it was declared rather than by a topo::Id, so any
+// component can read and write it and it is never purged.
+//
+// static CURRENT_USER: Atom<Option<User>> = atom!(None);
+//
+// let (user, user_access) = use_atom(&CURRENT_USER);
+use crate::store::{current_store, StateAccess};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+pub struct Atom<T> {
+    key: &'static str,
+    default: fn() -> T,
+}
+
+impl<T> Atom<T> {
+    // Use the atom! macro, which picks a key unique to the declaration.
+    pub const fn new(key: &'static str, default: fn() -> T) -> Atom<T> {
+        Atom { key, default }
+    }
+
+    pub fn key(&self) -> &'static str {
+        self.key
+    }
+
+    pub fn id(&self) -> topo::Id {
+        let mut hasher = DefaultHasher::new();
+        "comp_state::atom".hash(&mut hasher);
+        self.key.hash(&mut hasher);
+        topo::Id(hasher.finish())
+    }
+}
+
+#[macro_export]
+macro_rules! atom {
+    ($default:expr) => {
+        $crate::Atom::new(
+            concat!(module_path!(), ":", line!(), ":", column!()),
+            || $default,
+        )
+    };
+}
+
+// Not a hook, so it can be called conditionally and from outside of a render.
+pub fn use_atom<T: 'static + Clone>(atom: &Atom<T>) -> (T, StateAccess<T>) {
+    let id = atom.id();
+    let store = current_store().unwrap();
+    let existing = store.borrow_mut().get_state_with_topo_id::<T>(id).cloned();
+    let value = match existing {
+        Some(value) => value,
+        None => {
+            let value = (atom.default)();
+            let mut store_mut = store.borrow_mut();
+            store_mut.atom_ids.insert(id);
+            store_mut.set_state_with_topo_id(value.clone(), id);
+            value
+        }
+    };
+    (value, StateAccess::new(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestRenderer;
+
+    #[test]
+    fn atoms_are_shared_and_never_purged() {
+        static THEME: Atom<&str> = atom!("light");
+
+        let mut header = TestRenderer::new(|| use_atom(&THEME));
+        let (theme, theme_access) = header.render();
+        assert_eq!(theme, "light");
+        theme_access.set("dark");
+        header.unmount();
+        header.unmount();
+
+        let footer = || topo::call!(use_atom(&THEME).0);
+        assert_eq!(header.in_store(footer), "dark");
+        assert_eq!(TestRenderer::new(footer).render(), "light");
+    }
+}
@@ -0,0 +1,86 @@
+use crate::store::{current_store, next_hook_id};
+use std::fmt;
+
+pub struct EffectCleanup(Box<dyn FnOnce()>);
+
+impl EffectCleanup {
+    pub fn run(self) {
+        (self.0)()
+    }
+}
+
+impl fmt::Debug for EffectCleanup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("EffectCleanup")
+    }
+}
+
+// Runs `effect` on the first render and again whenever `deps` differs from the
+// previous render. The closure returned by `effect` is run before the effect runs
+// again, and when the component is no longer rendered and its state is purged.
+//
+// use_effect(url.clone(), || {
+//     let handle = start_polling(url);
+//     move || handle.cancel()
+// });
+pub fn use_effect<D, F, C>(deps: D, effect: F)
+where
+    D: 'static + Clone + PartialEq,
+    F: FnOnce() -> C,
+    C: FnOnce() + 'static,
+{
+    let hook_id = next_hook_id();
+    let store = current_store();
+    let store = store.unwrap();
+
+    let deps_changed = store.borrow_mut().get_state_with_topo_id::<D>(hook_id) != Some(&deps);
+    if !deps_changed {
+        return;
+    }
+
+    let previous_cleanup = store.borrow_mut().effect_cleanups.remove(&hook_id);
+    if let Some(cleanup) = previous_cleanup {
+        cleanup.run();
+    }
+
+    store.borrow_mut().set_state_with_topo_id(deps, hook_id);
+    let cleanup = effect();
+    store
+        .borrow_mut()
+        .effect_cleanups
+        .insert(hook_id, EffectCleanup(Box::new(cleanup)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Log;
+    use crate::{init_root_context, purge_and_reset_unseen_ids};
+
+    #[test]
+    fn effects_rerun_on_deps_change_and_clean_up_when_purged() {
+        init_root_context();
+        let log = Log::new();
+        let frame = |deps: u32, rendered: bool| {
+            topo::root!({
+                if rendered {
+                    let log = log.clone();
+                    use_effect(deps, move || {
+                        log.push(format!("run {}", deps));
+                        move || log.push(format!("cleanup {}", deps))
+                    });
+                }
+            });
+            purge_and_reset_unseen_ids();
+        };
+
+        frame(1, true);
+        frame(1, true);
+        frame(2, true);
+        frame(2, false);
+        assert_eq!(
+            log.events(),
+            vec!["run 1", "cleanup 1", "run 2", "cleanup 2"]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+// Drives the unseen id lifecycle for an app's view, in place of topo::root!.
+// Every id is marked unseen before the render, anything the render does not touch is
+// purged after it, so state for components that are no longer rendered is dropped.
+//
+// fn view(model: &Model) -> impl View<Msg> {
+//     comp_state::frame(|| div![...])
+// }
+use crate::store::{purge_unseen_ids, reset_unseen_id_list, StoreHandle};
+
+pub fn frame<R, F: FnOnce() -> R>(view: F) -> R {
+    reset_unseen_id_list();
+    let rendered = topo::root!(view());
+    purge_unseen_ids();
+    rendered
+}
+
+// Present in the topo Env while rendering within keep_alive, with the id of the call.
+pub(crate) struct KeepAlive(pub(crate) topo::Id);
+
+// State first created within `view` is never purged by a frame, even once `view` is
+// no longer rendered, e.g. the contents of a hidden tab, until the returned handle is
+// released.
+//
+// let (contents, kept_alive) = keep_alive(|| tab_contents());
+pub fn keep_alive<R, F: FnOnce() -> R>(view: F) -> (R, KeptAlive) {
+    topo::call!({
+        let kept_alive = KeptAlive {
+            id: topo::Id::current(),
+            store: StoreHandle::current(),
+        };
+        let rendered = topo::call!(
+            view(),
+            env! {
+                KeepAlive => KeepAlive(kept_alive.id),
+            }
+        );
+        (rendered, kept_alive)
+    })
+}
+
+// The same for every render of a keep_alive call, so it can be kept in state and
+// released once the contents are gone for good, e.g. when their tab is closed.
+#[derive(Clone, Debug)]
+pub struct KeptAlive {
+    pub id: topo::Id,
+    store: StoreHandle,
+}
+
+impl KeptAlive {
+    // The state is then purged by the next frame that does not render it, as any other.
+    // Does nothing once the store has gone.
+    pub fn release(&self) {
+        if let Ok(store) = self.store.get() {
+            store
+                .borrow_mut()
+                .kept_alive_ids
+                .retain(|_, kept_by| *kept_by != self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestRenderer;
+    use crate::{init_root_context, use_state, StateError};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn frames_purge_unrendered_state_unless_kept_alive() {
+        let tabs = |show: bool| {
+            frame(|| {
+                if show {
+                    let (_, visible_access) = use_state(|| 0);
+                    let ((_, hidden_access), _) = keep_alive(|| use_state(|| 0));
+                    Some((visible_access, hidden_access))
+                } else {
+                    None
+                }
+            })
+        };
+
+        init_root_context();
+        let (visible_access, hidden_access) = tabs(true).unwrap();
+        visible_access.set(1);
+        hidden_access.set(1);
+        assert!(tabs(true).is_some());
+        assert_eq!(visible_access.try_get(), Ok(1));
+
+        tabs(false);
+        tabs(false);
+        assert_eq!(visible_access.try_get(), Err(StateError::Missing));
+        assert_eq!(hidden_access.try_get(), Ok(1));
+    }
+
+    #[test]
+    fn released_state_is_purged_once_unrendered() {
+        let show = Rc::new(Cell::new(true));
+        let mut renderer = TestRenderer::new({
+            let show = show.clone();
+            move || {
+                if show.get() {
+                    Some(keep_alive(|| use_state(|| 0)))
+                } else {
+                    None
+                }
+            }
+        });
+
+        let ((_, hidden_access), kept_alive) = renderer.render().unwrap();
+        assert_eq!(renderer.rerender().unwrap().1.id, kept_alive.id);
+        show.set(false);
+        renderer.rerender();
+        assert_eq!(hidden_access.try_get(), Ok(0));
+
+        // outside of the renderer's store, as from an event handler.
+        kept_alive.release();
+        renderer.rerender();
+        assert_eq!(hidden_access.try_get(), Err(StateError::Missing));
+        assert!(renderer.store().borrow().kept_alive_ids.is_empty());
+    }
+}
@@ -0,0 +1,318 @@
+// Optional time-travel history for the Store.
+//
+// While recording, every change made through set_state_with_topo_id (and so every
+// StateAccess::set / update, use_list and form state change) is logged with the value it
+// replaced. The store can then be rolled backwards and forwards through those changes.
+// Initialising a slot for the first time is not a change and is not recorded.
+// Changes made within a transaction are undone and redone together.
+use crate::notify::notify_state_changed;
+use crate::store::{current_store, Store};
+use slotmap::{DefaultKey, SecondaryMap};
+use std::any::{Any, TypeId};
+use std::fmt;
+
+type ApplyFn = fn(&mut Store, topo::Id, &dyn Any);
+type CopyFn = fn(&dyn Any) -> Box<dyn Any>;
+
+pub struct HistoryEntry {
+    pub id: topo::Id,
+    pub type_name: &'static str,
+    pub old: Box<dyn Any>,
+    pub new: Box<dyn Any>,
+    pub transaction: Option<u64>,
+    apply: ApplyFn,
+}
+
+impl fmt::Debug for HistoryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HistoryEntry")
+            .field("id", &self.id)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+// `position` is the number of entries currently applied to the store,
+// entries after it are available to redo.
+#[derive(Debug, Default)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+    pub position: usize,
+}
+
+fn apply_value<T: 'static + Clone>(store: &mut Store, id: topo::Id, value: &dyn Any) {
+    if let Some(value) = value.downcast_ref::<T>() {
+        store.set_state_with_topo_id(value.clone(), id);
+    }
+}
+
+fn copy_value<T: 'static + Clone>(value: &dyn Any) -> Box<dyn Any> {
+    Box::new(value.downcast_ref::<T>().unwrap().clone())
+}
+
+// Copies and restores values of one type. Updates have no Clone bound, so the Store keeps
+// these for each type used with a StateAccess or set through set_state_with_topo_id, and
+// updates copy old values with them. Updates to values of any other type are not recorded
+// and are not rolled back by a transaction.
+#[derive(Clone, Copy, Debug)]
+pub struct ValueFns {
+    copy: CopyFn,
+    pub(crate) apply: ApplyFn,
+}
+
+impl Store {
+    pub fn start_recording(&mut self) {
+        if self.history.is_none() {
+            self.history = Some(History::default());
+        }
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.history = None;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.history.is_some()
+    }
+
+    // Logs `new` replacing the value currently stored for `id`.
+    // Any undone entries are dropped as they can no longer be redone.
+    pub fn record_set<T: 'static + Clone>(&mut self, new: &T, id: topo::Id) {
+        if self.history.is_none() {
+            return;
+        }
+        let old = match (
+            self.id_to_key_map.get(&id),
+            self.anymap.get::<SecondaryMap<DefaultKey, T>>(),
+        ) {
+            (Some(key), Some(sec_map)) => sec_map.get(*key).cloned(),
+            (_, _) => None,
+        };
+
+        if let Some(old) = old {
+            self.record_change(id, old, new.clone());
+        }
+    }
+
+    pub fn record_change<T: 'static + Clone>(&mut self, id: topo::Id, old: T, new: T) {
+        self.push_entry::<T>(id, Box::new(old), Box::new(new), apply_value::<T>);
+    }
+
+    // As record_change for values copied with copy_state.
+    pub fn record_copied_change<T: 'static>(
+        &mut self,
+        id: topo::Id,
+        old: Box<dyn Any>,
+        new: Box<dyn Any>,
+    ) {
+        if let Some(value_fns) = self.value_fns::<T>() {
+            self.push_entry::<T>(id, old, new, value_fns.apply);
+        }
+    }
+
+    fn push_entry<T: 'static>(
+        &mut self,
+        id: topo::Id,
+        old: Box<dyn Any>,
+        new: Box<dyn Any>,
+        apply: ApplyFn,
+    ) {
+        if let Some(history) = self.history.as_mut() {
+            history.entries.truncate(history.position);
+            history.entries.push(HistoryEntry {
+                id,
+                type_name: std::any::type_name::<T>(),
+                old,
+                new,
+                transaction: self
+                    .transaction
+                    .as_ref()
+                    .map(|transaction| transaction.number),
+                apply,
+            });
+            history.position = history.entries.len();
+        }
+    }
+
+    pub fn register_value_fns<T: 'static + Clone>(&mut self) {
+        self.value_fns.entry(TypeId::of::<T>()).or_insert(ValueFns {
+            copy: copy_value::<T>,
+            apply: apply_value::<T>,
+        });
+    }
+
+    pub fn value_fns<T: 'static>(&self) -> Option<ValueFns> {
+        self.value_fns.get(&TypeId::of::<T>()).copied()
+    }
+
+    // A copy of the value stored for `id`, None if there is none or its type has no ValueFns.
+    pub fn copy_state<T: 'static>(&self, id: topo::Id) -> Option<Box<dyn Any>> {
+        let value_fns = self.value_fns::<T>()?;
+        let value = self.peek_state_with_topo_id::<T>(id)?;
+        Some((value_fns.copy)(value))
+    }
+
+    // Rolls the store to the state it was in after the first `position` recorded changes.
+    // Returns false if not recording or `position` is past the end of the history.
+    pub fn jump_to(&mut self, position: usize) -> bool {
+        let mut history = match self.history.take() {
+            Some(history) => history,
+            None => return false,
+        };
+        let in_range = position <= history.entries.len();
+
+        if in_range {
+            while history.position > position {
+                history.position -= 1;
+                let entry = &history.entries[history.position];
+                (entry.apply)(self, entry.id, &*entry.old);
+            }
+            while history.position < position {
+                let entry = &history.entries[history.position];
+                (entry.apply)(self, entry.id, &*entry.new);
+                history.position += 1;
+            }
+        }
+
+        self.history = Some(history);
+        in_range
+    }
+
+    pub fn undo(&mut self) -> bool {
+        match self.undo_position() {
+            Some(position) => self.jump_to(position),
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        match self.redo_position() {
+            Some(position) => self.jump_to(position),
+            None => false,
+        }
+    }
+
+    // The position undo moves back to, before the whole of the last transaction
+    // if the last change was made in one.
+    pub fn undo_position(&self) -> Option<usize> {
+        let history = self.history.as_ref()?;
+        let mut position = history.position.checked_sub(1)?;
+        if let Some(number) = history.entries[position].transaction {
+            while position > 0 && history.entries[position - 1].transaction == Some(number) {
+                position -= 1;
+            }
+        }
+        Some(position)
+    }
+
+    // The position redo moves forward to, past the whole of the next transaction
+    // if the next change was made in one.
+    pub fn redo_position(&self) -> Option<usize> {
+        let history = self.history.as_ref()?;
+        let mut position = history.position;
+        let transaction = history.entries.get(position)?.transaction;
+        position += 1;
+        if transaction.is_some() {
+            while position < history.entries.len()
+                && history.entries[position].transaction == transaction
+            {
+                position += 1;
+            }
+        }
+        Some(position)
+    }
+
+    pub fn history_position(&self) -> Option<usize> {
+        self.history.as_ref().map(|history| history.position)
+    }
+
+    pub fn history_len(&self) -> Option<usize> {
+        self.history.as_ref().map(|history| history.entries.len())
+    }
+}
+
+pub fn start_recording() {
+    let store = current_store();
+    store.unwrap().borrow_mut().start_recording();
+}
+
+pub fn stop_recording() {
+    let store = current_store();
+    store.unwrap().borrow_mut().stop_recording();
+}
+
+pub fn undo() -> bool {
+    let store = current_store();
+    let position = store.unwrap().borrow().undo_position();
+    match position {
+        Some(position) => jump_to(position),
+        None => false,
+    }
+}
+
+pub fn redo() -> bool {
+    let store = current_store();
+    let position = store.unwrap().borrow().redo_position();
+    match position {
+        Some(position) => jump_to(position),
+        None => false,
+    }
+}
+
+pub fn jump_to(position: usize) -> bool {
+    let store = current_store();
+    let store = store.unwrap();
+    let changed_ids = {
+        let mut store_mut = store.borrow_mut();
+        let from = match store_mut.history_position() {
+            Some(from) => from,
+            None => return false,
+        };
+        if !store_mut.jump_to(position) {
+            return false;
+        }
+        let (start, end) = (from.min(position), from.max(position));
+        store_mut.history.as_ref().unwrap().entries[start..end]
+            .iter()
+            .map(|entry| entry.id)
+            .collect::<Vec<_>>()
+    };
+    for id in changed_ids {
+        notify_state_changed(&store, id);
+    }
+    true
+}
+
+pub fn history_position() -> Option<usize> {
+    let store = current_store();
+    store.unwrap().borrow().history_position()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{history, init_root_context, use_state};
+
+    #[test]
+    fn history_undoes_and_redoes_sets() {
+        init_root_context();
+        let (_, access) = topo::root!(use_state(|| "a".to_string()));
+        history::start_recording();
+        access.set("b".to_string());
+        access.update(|value| value.replace_range(.., "c"));
+
+        assert!(history::undo());
+        assert_eq!(access.get(), Some("b".to_string()));
+        assert!(history::undo());
+        assert_eq!(access.get(), Some("a".to_string()));
+        assert!(!history::undo());
+        assert!(history::redo());
+        assert_eq!(access.get(), Some("b".to_string()));
+
+        access.set("d".to_string());
+        assert!(!history::redo());
+        assert!(history::jump_to(0));
+        assert_eq!(access.get(), Some("a".to_string()));
+        assert!(history::jump_to(2));
+        assert_eq!(access.get(), Some("d".to_string()));
+    }
+}
@@ -0,0 +1,50 @@
+// Gives what is rendered within an identity based on a key rather than on its position,
+// so state stays with list items as they are added, removed or reordered.
+//
+// list.items_order.iter().zip(list.items()).map(|(key, item)| {
+//     keyed!(*key, item_view!(item))
+// })
+//
+// Any Hash value can be a key, e.g. a ListKey or a database id. Keys only need to be
+// unique among the items rendered from the same place.
+use std::hash::Hash;
+
+// Keys are scoped to the keyed! call site, so different lists within one component can
+// share keys.
+#[macro_export]
+macro_rules! keyed {
+    ($key:expr, $($body:tt)*) => {
+        $crate::topo::call!(slot: $key, { $($body)* })
+    };
+}
+
+// As keyed! but as a function, so keys are scoped to the enclosing component rather than
+// to the call site.
+pub fn use_keyed<K: Hash, R, F: FnOnce() -> R>(key: K, func: F) -> R {
+    topo::call!(slot: key, func())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{init_root_context, use_state};
+
+    #[test]
+    fn keyed_state_follows_its_key_when_reordered() {
+        init_root_context();
+        let render = |keys: &[&'static str]| {
+            topo::root!(keys
+                .iter()
+                .map(|key| keyed!(*key, use_state(|| *key).0))
+                .collect::<Vec<_>>())
+        };
+
+        assert_eq!(render(&["a", "b"]), vec!["a", "b"]);
+        assert_eq!(render(&["b", "a"]), vec!["b", "a"]);
+        let reversed = topo::root!(["b", "a"]
+            .iter()
+            .map(|key| use_keyed(*key, || use_state(|| *key).0))
+            .collect::<Vec<_>>());
+        assert_eq!(reversed, vec!["b", "a"]);
+    }
+}
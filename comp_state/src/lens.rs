@@ -0,0 +1,200 @@
+// Lenses let a StateAccess focus on part of the value in its slot, so a child component
+// can be handed write access to a single field of its parent's state.
+//
+// let (item, item_access) = use_state(|| Item::default());
+// let description_access = item_access.map(|item| &item.description, |item| &mut item.description);
+// description_access.set("new description".to_string());
+//
+// A focused access shares its slot and its id with the access it was mapped from.
+// Writes are made to the whole slot so they are recorded in history and notified as usual.
+use crate::store::{try_update_state_in, with_state_taken, StateAccess, StateError, Store};
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+// A path from a slot's value to a U within it.
+trait Focus<U> {
+    fn read(
+        &self,
+        store: &RefCell<Store>,
+        id: topo::Id,
+        func: &mut dyn FnMut(&U),
+    ) -> Result<(), StateError>;
+
+    fn update(
+        &self,
+        store: &RefCell<Store>,
+        id: topo::Id,
+        func: &mut dyn FnMut(&mut U),
+    ) -> Result<(), StateError>;
+}
+
+// The slot's value itself.
+struct Slot<T>(PhantomData<T>);
+
+impl<T: 'static + Clone> Focus<T> for Slot<T> {
+    fn read(
+        &self,
+        store: &RefCell<Store>,
+        id: topo::Id,
+        func: &mut dyn FnMut(&T),
+    ) -> Result<(), StateError> {
+        with_state_taken::<T, _, _>(store, id, |value| func(value))
+    }
+
+    fn update(
+        &self,
+        store: &RefCell<Store>,
+        id: topo::Id,
+        func: &mut dyn FnMut(&mut T),
+    ) -> Result<(), StateError> {
+        try_update_state_in::<T, _, _>(store, id, func)
+    }
+}
+
+struct Field<T, U> {
+    parent: Rc<dyn Focus<T>>,
+    get: fn(&T) -> &U,
+    get_mut: fn(&mut T) -> &mut U,
+}
+
+impl<T, U> Focus<U> for Field<T, U> {
+    fn read(
+        &self,
+        store: &RefCell<Store>,
+        id: topo::Id,
+        func: &mut dyn FnMut(&U),
+    ) -> Result<(), StateError> {
+        let get = self.get;
+        self.parent.read(store, id, &mut |value| func(get(value)))
+    }
+
+    fn update(
+        &self,
+        store: &RefCell<Store>,
+        id: topo::Id,
+        func: &mut dyn FnMut(&mut U),
+    ) -> Result<(), StateError> {
+        let get_mut = self.get_mut;
+        self.parent
+            .update(store, id, &mut |value| func(get_mut(value)))
+    }
+}
+
+pub(crate) struct Lens<T>(Rc<dyn Focus<T>>);
+
+// A lens only holds its focus, so it is Clone and Debug whatever T is.
+impl<T> Clone for Lens<T> {
+    fn clone(&self) -> Self {
+        Lens(Rc::clone(&self.0))
+    }
+}
+
+impl<T> fmt::Debug for Lens<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Lens")
+    }
+}
+
+impl<T: 'static + Clone> Lens<T> {
+    pub(crate) fn try_get(&self, store: &RefCell<Store>, id: topo::Id) -> Result<T, StateError> {
+        self.try_with(store, id, |value| value.clone())
+    }
+}
+
+impl<T: 'static> Lens<T> {
+    pub(crate) fn try_with<F: FnOnce(&T) -> R, R>(
+        &self,
+        store: &RefCell<Store>,
+        id: topo::Id,
+        func: F,
+    ) -> Result<R, StateError> {
+        let mut func = Some(func);
+        let mut result = None;
+        self.0.read(store, id, &mut |value| {
+            result = func.take().map(|func| func(value))
+        })?;
+        Ok(result.unwrap())
+    }
+
+    pub(crate) fn try_update<F: FnOnce(&mut T) -> R, R>(
+        &self,
+        store: &RefCell<Store>,
+        id: topo::Id,
+        func: F,
+    ) -> Result<R, StateError> {
+        let mut func = Some(func);
+        let mut result = None;
+        self.0.update(store, id, &mut |value| {
+            result = func.take().map(|func| func(value))
+        })?;
+        Ok(result.unwrap())
+    }
+}
+
+impl<T> StateAccess<T>
+where
+    T: 'static + Clone,
+{
+    // Focuses on the part of the value picked out by `get` and `get_mut`,
+    // which are usually just field accesses.
+    pub fn map<U: 'static + Clone>(
+        &self,
+        get: fn(&T) -> &U,
+        get_mut: fn(&mut T) -> &mut U,
+    ) -> StateAccess<U> {
+        let parent = match &self.lens {
+            Some(lens) => Rc::clone(&lens.0),
+            None => Rc::new(Slot::<T>(PhantomData)) as Rc<dyn Focus<T>>,
+        };
+        StateAccess {
+            id: self.id,
+            store: self.store.clone(),
+            lens: Some(Lens(Rc::new(Field {
+                parent,
+                get,
+                get_mut,
+            }))),
+            _phantom_data: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{history, init_root_context, use_state};
+
+    #[test]
+    fn mapped_state_access_focuses_on_a_field() {
+        #[derive(Clone, Default)]
+        struct Details {
+            description: String,
+        }
+        #[derive(Clone, Default)]
+        struct Item {
+            title: String,
+            details: Details,
+        }
+
+        init_root_context();
+        history::start_recording();
+        let (_, item_access) = topo::root!(use_state(Item::default));
+        let details_access = item_access.map(|item| &item.details, |item| &mut item.details);
+        let description_access = details_access.map(
+            |details| &details.description,
+            |details| &mut details.description,
+        );
+
+        item_access.set(Item::default());
+        description_access.set("new".to_string());
+        description_access.update(|description| description.push('!'));
+        item_access.update(|item| item.title = "title".to_string());
+        assert_eq!(description_access.hard_get(), "new!");
+        assert_eq!(item_access.hard_get().details.description, "new!");
+
+        history::undo();
+        history::undo();
+        assert_eq!(description_access.hard_get(), "new");
+    }
+}
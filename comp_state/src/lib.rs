@@ -1,25 +1,69 @@
 pub mod actor;
+mod atom;
 mod context;
+mod effect;
+mod frame;
+pub mod history;
+mod keyed;
+mod lens;
 mod list;
 mod memo;
+mod notify;
+#[cfg(feature = "serde")]
+mod persist;
+mod reducer;
+mod selector;
+#[cfg(feature = "serde")]
+mod snapshot;
+mod state_ref;
 mod store;
+pub mod testing;
+mod transaction;
 
+pub use atom::{use_atom, Atom};
 pub use context::do_once;
 pub use context::use_parent_memo;
 pub use context::{get_context, set_context};
-pub use list::{use_list, List, ListControl};
-pub use memo::{use_memo, watch};
+pub use effect::use_effect;
+pub use frame::{frame, keep_alive, KeptAlive};
+pub use keyed::use_keyed;
+pub use list::{use_list, List, ListControl, ListKey, ListView, SelectionMode};
+pub use memo::{
+    use_memo, use_memo_cache, use_memo_deps, use_previous, watch, watch_by, watch_key, MemoCache,
+};
+pub use notify::{clear_state_changed_notifier, set_state_changed_notifier};
+#[cfg(feature = "serde")]
+pub use persist::{
+    set_storage_backend, use_persisted_state, use_versioned_persisted_state, MemoryStorage,
+    Migration, PersistedStateAccess, StorageBackend,
+};
+pub use reducer::{use_reducer, Dispatcher};
+pub use selector::use_selector;
+#[cfg(feature = "serde")]
+pub use snapshot::{
+    register_serializable_state, restore_store, snapshot_store, SnapshotError, StateSnapshot,
+    StoreSnapshot,
+};
+pub use state_ref::{use_state_ref, StateRef};
 pub use store::init_root_context;
 
 pub use store::state_getter;
 pub use store::StateAccess;
+pub use store::StateError;
 pub use store::Store;
 
 pub use store::{
-    clone_state, get_state_with_topo_id, purge_and_reset_unseen_ids, set_state,
-    set_state_with_topo_id, update_state_with_topo_id, use_state,
+    clone_state, current_store, get_state_with_topo_id, new_store, purge_and_reset_unseen_ids,
+    set_state, set_state_with_topo_id, try_get_state_with_topo_id, try_set_state_with_topo_id,
+    try_update_state_with_topo_id, update_state_with_topo_id, use_state, with_store,
 };
 
+pub use transaction::{transaction, try_transaction};
+
+// used by the keyed! macro.
+#[doc(hidden)]
+pub use topo;
+
 #[cfg(test)]
 mod tests {
     #[test]
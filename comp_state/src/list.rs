@@ -1,5 +1,6 @@
 use crate::{use_state, StateAccess};
-use slotmap::{new_key_type, DenseSlotMap, Key};
+use slotmap::{new_key_type, DenseSlotMap};
+use std::cmp::Ordering;
 
 new_key_type! {
     pub struct ListKey;
@@ -39,6 +40,7 @@ where
         self.list_access.update(|list| {
             list.items_map = ListKeyDenseSlotMap::new();
             list.items_order = vec![];
+            list.selection.keys.clear();
         });
     }
 
@@ -72,19 +74,18 @@ where
     // 0 1 2 3 4 5 6
     // a b d e f g
     pub fn move_item_to_position(&self, old_idx: usize, new_idx: usize) {
-        let mut list = self.list_access.get().unwrap();
-        if new_idx > list.items_order.len() || old_idx > list.items_order.len() - 1 {
-            return;
-        }
-
-        let old_item = list.items_order.remove(old_idx);
-        use std::cmp::Ordering;
-        match old_idx.cmp(&new_idx) {
-            Ordering::Less => list.items_order.insert(new_idx - 1, old_item),
-            Ordering::Greater => list.items_order.insert(new_idx, old_item),
-            Ordering::Equal => {}
-        }
-        self.list_access.set(list);
+        self.list_access.update(|list| {
+            if new_idx > list.items_order.len() || old_idx >= list.items_order.len() {
+                return;
+            }
+
+            let old_item = list.items_order.remove(old_idx);
+            match old_idx.cmp(&new_idx) {
+                Ordering::Less => list.items_order.insert(new_idx - 1, old_item),
+                Ordering::Greater => list.items_order.insert(new_idx, old_item),
+                Ordering::Equal => {}
+            }
+        });
     }
 
     pub fn move_item_up(&self, old_idx: usize) {
@@ -99,116 +100,258 @@ where
     }
 
     pub fn insert(&self, idx: usize, item: T) {
-        let mut list = self.list_access.get().unwrap();
-        let inserted_key = list.items_map.0.insert(item);
-        list.items_order.insert(idx, inserted_key);
-        self.list_access.set(list);
+        self.list_access.update(|list| {
+            let inserted_key = list.items_map.0.insert(item);
+            list.items_order.insert(idx, inserted_key);
+        });
     }
 
     pub fn remove(&self, idx: usize) -> T {
-        let mut list = self.list_access.get().unwrap();
-        let removed_key = list.items_order.remove(idx);
-        let obj = list.items_map.0.remove(removed_key).unwrap();
-        self.list_access.set(list);
-        obj
+        self.list_access
+            .try_update(|list| {
+                let removed_key = list.items_order.remove(idx);
+                list.selection.unselect(removed_key);
+                list.items_map.0.remove(removed_key).unwrap()
+            })
+            .unwrap()
     }
 
     pub fn replace(&self, idx: usize, item: T) -> T {
-        let mut list = self.list_access.get().unwrap();
-        let inserted_key = list.items_map.0.insert(item);
-        list.items_order.insert(idx, inserted_key);
-        let replaced_key = list.items_order.remove(idx + 1);
-        let obj = list.items_map.0.remove(replaced_key).unwrap();
-        self.list_access.set(list);
-        obj
+        self.list_access
+            .try_update(|list| {
+                let inserted_key = list.items_map.0.insert(item);
+                list.items_order.insert(idx, inserted_key);
+                let replaced_key = list.items_order.remove(idx + 1);
+                // the new item takes over the replaced item's place in the selection
+                for key in list.selection.keys.iter_mut() {
+                    if *key == replaced_key {
+                        *key = inserted_key;
+                    }
+                }
+                list.items_map.0.remove(replaced_key).unwrap()
+            })
+            .unwrap()
     }
 
-    pub fn push(&self, item: T) {
-        let mut list = self.list_access.get().unwrap();
-        let pushed_key = list.items_map.0.insert(item);
-        list.items_order.push(pushed_key);
-        self.list_access.set(list);
+    // The by_key methods address an item by its key, which unlike its index stays the same
+    // as other items are added, removed or moved. Use them in handlers that may run after
+    // the list has changed.
+    pub fn get_by_key(&self, key: ListKey) -> Option<T> {
+        self.list_access.with(|list| list.get_by_key(key).cloned())
     }
 
-    pub fn unselect_by_key(&self, key: ListKey) {
-        let mut list = self.list_access.get().unwrap();
+    pub fn index_of(&self, key: ListKey) -> Option<usize> {
+        self.list_access.with(|list| list.index_of(key))
+    }
 
-        list.selected_keys.retain(|k| *k != key);
+    pub fn remove_by_key(&self, key: ListKey) -> Option<T> {
+        self.list_access
+            .try_update(|list| {
+                let idx = list.index_of(key)?;
+                list.items_order.remove(idx);
+                list.selection.unselect(key);
+                list.items_map.0.remove(key)
+            })
+            .unwrap()
+    }
 
-        self.list_access.set(list);
+    // The item keeps its key, unlike with replace.
+    pub fn replace_by_key(&self, key: ListKey, item: T) -> Option<T> {
+        self.list_access
+            .try_update(|list| {
+                list.items_map
+                    .0
+                    .get_mut(key)
+                    .map(|existing| std::mem::replace(existing, item))
+            })
+            .unwrap()
     }
 
-    pub fn unselect_all(&self) {
-        let mut list = self.list_access.get().unwrap();
-        list.selected_keys = vec![];
-        self.list_access.set(list);
+    // Moves the item for `key` to just before the item for `other`.
+    pub fn move_key_before(&self, key: ListKey, other: ListKey) {
+        self.list_access.update(|list| {
+            if key == other || list.index_of(other).is_none() {
+                return;
+            }
+            if let Some(idx) = list.index_of(key) {
+                list.items_order.remove(idx);
+                let other_idx = list.index_of(other).unwrap();
+                list.items_order.insert(other_idx, key);
+            }
+        });
     }
 
-    pub fn select_all(&self) {
-        let mut list = self.list_access.get().unwrap();
-        for key in &list.items_order {
-            list.selected_keys.push(*key)
-        }
+    pub fn push(&self, item: T) {
+        self.list_access.update(|list| {
+            let pushed_key = list.items_map.0.insert(item);
+            list.items_order.push(pushed_key);
+        });
+    }
 
-        self.list_access.set(list);
+    pub fn selection_mode(&self) -> SelectionMode {
+        self.list_access.with(|list| list.selection.mode)
     }
 
-    pub fn unselect(&self, idx: usize) {
-        let mut list = self.list_access.get().unwrap();
+    // Switching to Single or Range keeps only the most recently selected item.
+    pub fn set_selection_mode(&self, mode: SelectionMode) {
+        self.list_access.update(|list| {
+            let selection = &mut list.selection;
+            selection.mode = mode;
+            if mode != SelectionMode::Multi {
+                let last = selection.keys.pop();
+                selection.keys = last.into_iter().collect();
+            }
+        });
+    }
 
-        list.selected_keys.remove(idx);
+    pub fn unselect_by_key(&self, key: ListKey) {
+        self.list_access.update(|list| list.selection.unselect(key));
+    }
 
-        self.list_access.set(list);
+    pub fn unselect_all(&self) {
+        self.list_access.update(|list| list.selection.keys.clear());
+    }
+
+    // Does nothing in Single mode.
+    pub fn select_all(&self) {
+        self.list_access.update(|list| {
+            if list.selection.mode != SelectionMode::Single {
+                list.selection.keys = list.items_order.clone();
+            }
+        });
     }
-    pub fn select(&self, idx: usize) {
-        let mut list = self.list_access.get().unwrap();
 
-        let key = list.items_order[idx];
-        list.selected_keys.push(key);
+    pub fn unselect(&self, idx: usize) {
+        if let Some(key) = self.key_at(idx) {
+            self.unselect_by_key(key);
+        }
+    }
 
-        self.list_access.set(list);
+    pub fn select(&self, idx: usize) {
+        if let Some(key) = self.key_at(idx) {
+            self.select_by_key(key);
+        }
     }
 
     pub fn toggle_select(&self, idx: usize) {
-        let mut list = self.list_access.get().unwrap();
+        if let Some(key) = self.key_at(idx) {
+            self.toggle_select_by_key(key);
+        }
+    }
 
-        let key = list.items_order[idx];
-        if list.selected_keys.contains(&key) {
-            list.selected_keys.remove(idx);
-        } else {
-            list.selected_keys.push(key);
+    pub fn select_only(&self, idx: usize) {
+        if let Some(key) = self.key_at(idx) {
+            self.select_only_by_key(key);
         }
+    }
 
-        self.list_access.set(list);
+    pub fn select_only_by_key(&self, key: ListKey) {
+        self.list_access.update(|list| {
+            if list.items_map.0.contains_key(key) {
+                list.selection.keys = vec![key];
+            }
+        });
     }
 
-    pub fn select_only(&self, idx: usize) {
-        let mut list = self.list_access.get().unwrap();
+    // Adds to the selection in Multi mode, otherwise replaces it.
+    pub fn select_by_key(&self, key: ListKey) {
+        self.list_access.update(|list| {
+            if list.items_map.0.contains_key(key) {
+                list.selection.select(key);
+            }
+        });
+    }
 
-        let key = list.items_order[idx];
-        list.selected_keys = vec![];
-        list.selected_keys.push(key);
+    // In Range mode toggling a selected item clears the selection, as unselecting it
+    // alone could split the range.
+    pub fn toggle_select_by_key(&self, key: ListKey) {
+        self.list_access.update(|list| {
+            if list.selection.is_selected(key) {
+                if list.selection.mode == SelectionMode::Range {
+                    list.selection.keys.clear();
+                } else {
+                    list.selection.unselect(key);
+                }
+            } else if list.items_map.0.contains_key(key) {
+                list.selection.select(key);
+            }
+        });
+    }
 
-        self.list_access.set(list);
+    // Selects every item from `from` to `to` inclusive, in list order, as for a shift-click.
+    // Adds to the selection in Multi mode, replaces it in Range mode and selects only `to`
+    // in Single mode.
+    pub fn select_range(&self, from: ListKey, to: ListKey) {
+        self.list_access.update(|list| {
+            let (from_idx, to_idx) = match (list.index_of(from), list.index_of(to)) {
+                (Some(from_idx), Some(to_idx)) => (from_idx, to_idx),
+                _ => return,
+            };
+            let range = if from_idx <= to_idx {
+                &list.items_order[from_idx..=to_idx]
+            } else {
+                &list.items_order[to_idx..=from_idx]
+            };
+            let selection = &mut list.selection;
+            match selection.mode {
+                SelectionMode::Single => selection.keys = vec![to],
+                SelectionMode::Range => selection.keys = range.to_vec(),
+                SelectionMode::Multi => {
+                    for key in range {
+                        selection.select(*key);
+                    }
+                }
+            }
+        });
     }
 
-    pub fn select_only_by_key(&self, key: ListKey) {
-        let mut list = self.list_access.get().unwrap();
-        if !key.is_null() {
-            list.selected_keys = vec![];
-            list.selected_keys.push(key);
-        }
-        self.list_access.set(list);
+    fn key_at(&self, idx: usize) -> Option<ListKey> {
+        self.list_access
+            .with(|list| list.items_order.get(idx).cloned())
     }
+}
 
-    pub fn select_by_key(&self, key: ListKey) {
-        let mut list = self.list_access.get().unwrap();
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SelectionMode {
+    // at most one item is selected
+    Single,
+    // any items may be selected
+    Multi,
+    // the selected items are one contiguous run, chosen with select_range
+    Range,
+}
+
+// by hand as #[default] on a variant needs a newer compiler than the crate otherwise does.
+#[allow(clippy::derivable_impls)]
+impl Default for SelectionMode {
+    fn default() -> SelectionMode {
+        SelectionMode::Multi
+    }
+}
 
-        if !key.is_null() {
-            list.selected_keys.push(key);
+// The selected keys, most recently selected last, without duplicates.
+#[derive(Clone, Default, PartialEq)]
+struct ListSelection {
+    mode: SelectionMode,
+    keys: Vec<ListKey>,
+}
+
+impl ListSelection {
+    fn is_selected(&self, key: ListKey) -> bool {
+        self.keys.contains(&key)
+    }
+
+    fn select(&mut self, key: ListKey) {
+        if self.mode != SelectionMode::Multi {
+            self.keys.clear();
         }
+        if !self.is_selected(key) {
+            self.keys.push(key);
+        }
+    }
 
-        self.list_access.set(list);
+    fn unselect(&mut self, key: ListKey) {
+        self.keys.retain(|k| *k != key);
     }
 }
 
@@ -228,7 +371,7 @@ where
 {
     pub items_map: ListKeyDenseSlotMap<T>,
     pub items_order: Vec<ListKey>,
-    selected_keys: Vec<ListKey>,
+    selection: ListSelection,
 }
 
 impl<T> PartialEq for ListKeyDenseSlotMap<T>
@@ -258,7 +401,7 @@ where
         List {
             items_map: ListKeyDenseSlotMap(sm),
             items_order: keys,
-            selected_keys: vec![],
+            selection: ListSelection::default(),
         }
     }
 
@@ -268,11 +411,231 @@ where
             .iter()
             .filter_map(move |list_key| self.items_map.0.get(*list_key))
     }
-    // an iterator over all selected items
+    // an iterator over all items in the list along with their keys
+    pub fn keyed_items(&self) -> impl Iterator<Item = (ListKey, &T)> {
+        self.items_order.iter().filter_map(move |list_key| {
+            self.items_map
+                .0
+                .get(*list_key)
+                .map(|item| (*list_key, item))
+        })
+    }
+
+    pub fn get_by_key(&self, key: ListKey) -> Option<&T> {
+        self.items_map.0.get(key)
+    }
+
+    pub fn index_of(&self, key: ListKey) -> Option<usize> {
+        self.items_order
+            .iter()
+            .position(|list_key| *list_key == key)
+    }
+
+    pub fn selection_mode(&self) -> SelectionMode {
+        self.selection.mode
+    }
+
+    pub fn is_selected(&self, key: ListKey) -> bool {
+        self.selection.is_selected(key)
+    }
+
+    // the keys of all selected items, in list order
+    pub fn selected_keys(&self) -> impl Iterator<Item = ListKey> + '_ {
+        self.items_order
+            .iter()
+            .cloned()
+            .filter(move |key| self.selection.is_selected(*key))
+    }
+
+    // an iterator over all selected items, in list order
     pub fn selected(&self) -> impl Iterator<Item = &T> {
         let items_map = &self.items_map.0;
-        self.selected_keys
-            .iter()
-            .filter_map(move |key| items_map.get(*key))
+        self.selected_keys()
+            .filter_map(move |key| items_map.get(key))
+    }
+
+    // A sorted and/or filtered projection of the list, borrowing its items.
+    pub fn view(&self) -> ListView<'_, T> {
+        ListView::new(self)
+    }
+}
+
+// The items of a List in an order and selection of their own, leaving items_order as it is.
+// Items keep their keys, so a view's items can still be changed through the ListControl.
+//
+// let active = list
+//     .view()
+//     .filter(|item| item.status == Status::Todo)
+//     .sort_by(|a, b| a.description.cmp(&b.description));
+// for (key, item) in active.iter() { ... }
+#[derive(Clone)]
+pub struct ListView<'a, T>
+where
+    T: Clone + 'static,
+{
+    list: &'a List<T>,
+    keys: Vec<ListKey>,
+}
+
+impl<'a, T> ListView<'a, T>
+where
+    T: Clone + 'static,
+{
+    pub fn new(list: &'a List<T>) -> ListView<'a, T> {
+        let keys = list.items_order.clone();
+        ListView { list, keys }
+    }
+
+    // Keeps only the items for which `predicate` is true. Filters combine.
+    pub fn filter<F: Fn(&T) -> bool>(mut self, predicate: F) -> ListView<'a, T> {
+        let items_map = &self.list.items_map.0;
+        self.keys.retain(|key| predicate(&items_map[*key]));
+        self
+    }
+
+    // The sort is stable, so items that compare equal keep their order from
+    // any earlier sort or else from the list.
+    pub fn sort_by<F: Fn(&T, &T) -> Ordering>(mut self, compare: F) -> ListView<'a, T> {
+        let items_map = &self.list.items_map.0;
+        self.keys
+            .sort_by(|a, b| compare(&items_map[*a], &items_map[*b]));
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ListKey, &'a T)> + '_ {
+        let items_map = &self.list.items_map.0;
+        self.keys.iter().map(move |key| (*key, &items_map[*key]))
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &'a T> + '_ {
+        self.iter().map(|(_, item)| item)
+    }
+
+    pub fn keys(&self) -> &[ListKey] {
+        &self.keys
+    }
+
+    // The key of the item at `idx` in the view.
+    pub fn key_at(&self, idx: usize) -> Option<ListKey> {
+        self.keys.get(idx).cloned()
+    }
+
+    // The position of `key` in the view, None if it is filtered out.
+    pub fn index_of(&self, key: ListKey) -> Option<usize> {
+        self.keys.iter().position(|k| *k == key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // The underlying list, e.g. to find an item's index in items_order.
+    pub fn list(&self) -> &'a List<T> {
+        self.list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init_root_context;
+
+    #[test]
+    fn list_items_can_be_addressed_by_key() {
+        init_root_context();
+        let (list, list_control) = topo::root!(use_list(|| vec!["a", "b", "c", "d"]));
+        let keys = list.keyed_items().map(|(key, _)| key).collect::<Vec<_>>();
+        let items = || list_control.get_list().items().cloned().collect::<Vec<_>>();
+
+        list_control.move_item_up(1);
+        assert_eq!(list_control.index_of(keys[1]), Some(0));
+        assert_eq!(list_control.remove_by_key(keys[0]), Some("a"));
+        assert_eq!(list_control.remove_by_key(keys[0]), None);
+        assert_eq!(list_control.replace_by_key(keys[2], "C"), Some("c"));
+        assert_eq!(list_control.get_by_key(keys[2]), Some("C"));
+        list_control.move_key_before(keys[3], keys[1]);
+        assert_eq!(items(), vec!["d", "b", "C"]);
+        list_control.move_key_before(keys[1], keys[3]);
+        assert_eq!(items(), vec!["b", "d", "C"]);
+    }
+
+    #[test]
+    fn list_selection_modes_and_pruning() {
+        init_root_context();
+        let (list, list_control) = topo::root!(use_list(|| vec!["a", "b", "c", "d", "e"]));
+        let keys = list.keyed_items().map(|(key, _)| key).collect::<Vec<_>>();
+        let selected = || {
+            list_control
+                .get_list()
+                .selected()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        list_control.select(3);
+        list_control.select(1);
+        list_control.select(1);
+        assert_eq!(selected(), vec!["b", "d"]);
+        list_control.toggle_select(3);
+        assert_eq!(selected(), vec!["b"]);
+        list_control.select_range(keys[4], keys[2]);
+        assert_eq!(selected(), vec!["b", "c", "d", "e"]);
+        list_control.remove_by_key(keys[2]);
+        list_control.remove(2);
+        assert!(!list_control.get_list().is_selected(keys[2]));
+        assert_eq!(selected(), vec!["b", "e"]);
+
+        list_control.set_selection_mode(SelectionMode::Single);
+        assert_eq!(selected(), vec!["e"]);
+        list_control.select(0);
+        list_control.select_all();
+        assert_eq!(selected(), vec!["a"]);
+
+        list_control.set_selection_mode(SelectionMode::Range);
+        list_control.select_range(keys[0], keys[4]);
+        list_control.select_range(keys[1], keys[4]);
+        assert_eq!(
+            list_control.get_list().selected_keys().collect::<Vec<_>>(),
+            vec![keys[1], keys[4]]
+        );
+        list_control.toggle_select_by_key(keys[4]);
+        assert_eq!(selected(), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn list_views_sort_and_filter_without_reordering_the_list() {
+        init_root_context();
+        let (list, list_control) =
+            topo::root!(use_list(|| vec!["pear", "fig", "apple", "kiwi", "date"]));
+
+        let view = list
+            .view()
+            .filter(|item| item.len() > 3)
+            .sort_by(|a, b| a.cmp(b));
+        assert_eq!(
+            view.items().cloned().collect::<Vec<_>>(),
+            vec!["apple", "date", "kiwi", "pear"]
+        );
+        assert_eq!(view.len(), 4);
+
+        let key = view.key_at(1).unwrap();
+        assert_eq!(view.index_of(key), Some(1));
+        assert_eq!(list_control.index_of(key), Some(4));
+        list_control.remove_by_key(key);
+
+        let list = list_control.get_list();
+        let view = list.view().sort_by(|a, b| a.len().cmp(&b.len()));
+        assert_eq!(
+            view.items().cloned().collect::<Vec<_>>(),
+            vec!["fig", "pear", "kiwi", "apple"]
+        );
+        assert_eq!(
+            list.items().cloned().collect::<Vec<_>>(),
+            vec!["pear", "fig", "apple", "kiwi"]
+        );
     }
 }
@@ -1,4 +1,9 @@
-use crate::{use_state, StateAccess};
+use crate::store::{current_store, next_hook_id, StoreHandle};
+use crate::{use_state, StateAccess, StateError};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
 
 #[derive(Clone)]
 pub struct MemoControl(StateAccess<bool>);
@@ -30,14 +35,21 @@ where
 }
 
 pub fn watch<T: 'static + Clone + PartialEq>(current_watched: &T) -> Watch<T> {
+    watch_by(current_watched, |watched, current| watched == current)
+}
+
+// As watch but `same` decides whether the value is unchanged, given the watched value
+// and the current one.
+//
+// let item = watch_by(&item, |watched, current| watched.version == current.version);
+pub fn watch_by<T, F>(current_watched: &T, same: F) -> Watch<T>
+where
+    T: 'static + Clone,
+    F: FnOnce(&T, &T) -> bool,
+{
     topo::call!({
-        let current_watched_clone = current_watched.clone();
         let (watched, watch_access) = use_state(|| current_watched.clone());
-        if watched != current_watched_clone {
-            // log!(format!(
-            //     "watched:{:#?} changed to {:#?}",
-            //     watched, current_watched_clone
-            // ));
+        if !same(&watched, current_watched) {
             watch_access.set(current_watched.clone());
             Watch::new(true, watch_access)
         } else {
@@ -46,6 +58,31 @@ pub fn watch<T: 'static + Clone + PartialEq>(current_watched: &T) -> Watch<T> {
     })
 }
 
+// As watch but only compares the keys picked out by `key`, e.g. an id or version field,
+// rather than the whole value.
+//
+// let item = watch_key(&item, |item| item.id);
+pub fn watch_key<T, K, F>(current_watched: &T, key: F) -> Watch<T>
+where
+    T: 'static + Clone,
+    K: PartialEq,
+    F: Fn(&T) -> K,
+{
+    watch_by(current_watched, |watched, current| {
+        key(watched) == key(current)
+    })
+}
+
+// The value passed in on the previous render, None on the first.
+pub fn use_previous<T: 'static + Clone>(value: &T) -> Option<T> {
+    let hook_id = next_hook_id();
+    let store = current_store().unwrap();
+    let mut store_mut = store.borrow_mut();
+    let previous = store_mut.get_state_with_topo_id::<T>(hook_id).cloned();
+    store_mut.set_state_with_topo_id(value.clone(), hook_id);
+    previous
+}
+
 pub fn use_memo<T: 'static + Clone, F: Fn() -> T>(recalc: bool, func: F) -> (T, MemoControl) {
     topo::call!({
         let (update, recalc_trigger_access) = use_state(|| false);
@@ -66,3 +103,289 @@ pub fn use_memo<T: 'static + Clone, F: Fn() -> T>(recalc: bool, func: F) -> (T,
         (value, MemoControl(recalc_trigger_access))
     })
 }
+
+// Recomputes the value only when `deps` differs from the previous render, so there is
+// no need to watch each input and combine the changed flags.
+// `func` is given the current deps, a tuple can be used for more than one.
+//
+// let nodes = use_memo_deps((item.clone(), idx), |(item, idx)| item_view(item, *idx));
+pub fn use_memo_deps<D, T, F>(deps: D, func: F) -> T
+where
+    D: 'static + Clone + PartialEq,
+    T: 'static + Clone,
+    F: FnOnce(&D) -> T,
+{
+    let hook_id = next_hook_id();
+    let store = current_store().unwrap();
+    let memoized = match store.borrow_mut().get_state_with_topo_id::<(D, T)>(hook_id) {
+        Some((memoized_deps, value)) if *memoized_deps == deps => Some(value.clone()),
+        _ => None,
+    };
+    if let Some(value) = memoized {
+        return value;
+    }
+
+    let value = func(&deps);
+    store
+        .borrow_mut()
+        .set_state_with_topo_id((deps, value.clone()), hook_id);
+    value
+}
+
+// The entries of a MemoCache, kept in the component's slot.
+struct CacheEntries<K, V> {
+    capacity: usize,
+    // each value with the tick it was last used at, the lowest is evicted first.
+    entries: HashMap<K, (V, u64)>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> CacheEntries<K, V> {
+    fn new(capacity: usize) -> CacheEntries<K, V> {
+        CacheEntries {
+            capacity,
+            entries: HashMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<V> {
+        self.clock += 1;
+        let clock = self.clock;
+        match self.entries.get_mut(key) {
+            Some((value, last_used)) => {
+                *last_used = clock;
+                self.hits += 1;
+                Some(value.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.clock += 1;
+        self.entries.insert(key, (value, self.clock));
+        self.evict();
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.entries.len() > self.capacity {
+            let least_recent = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(key) = least_recent {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+// Memoizes a function over several inputs at once, keeping the `capacity` most recently
+// used results. Unlike use_memo, alternating between a few inputs does not recompute.
+//
+// let cache = use_memo_cache(4);
+// let visible = cache.get_or_compute(filter, || filtered_items(&list, filter));
+pub struct MemoCache<K, V> {
+    pub id: topo::Id,
+    store: StoreHandle,
+    _phantom_data: PhantomData<(K, V)>,
+}
+
+// A MemoCache only refers to its slot, so it is Clone whatever K and V are.
+impl<K, V> Clone for MemoCache<K, V> {
+    fn clone(&self) -> Self {
+        MemoCache {
+            id: self.id,
+            store: self.store.clone(),
+            _phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for MemoCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemoCache").field("id", &self.id).finish()
+    }
+}
+
+impl<K, V> MemoCache<K, V>
+where
+    K: 'static + Hash + Eq + Clone,
+    V: 'static + Clone,
+{
+    fn with_entries<F: FnOnce(&mut CacheEntries<K, V>) -> R, R>(
+        &self,
+        func: F,
+    ) -> Result<R, StateError> {
+        let store = self.store.get()?;
+        let result = store.borrow_mut().with_state_mut(self.id, func);
+        result
+    }
+
+    // `func` is only run on a miss. Once the cache's slot has been purged, e.g. when used
+    // from an event handler after its component has gone, `func` is run every time.
+    pub fn get_or_compute<F: FnOnce() -> V>(&self, key: K, func: F) -> V {
+        if let Ok(Some(value)) = self.with_entries(|entries| entries.get(&key)) {
+            return value;
+        }
+        let value = func();
+        // errors only if the slot has gone, when there is nothing left to fill.
+        let _ = self.with_entries(|entries| entries.insert(key, value.clone()));
+        value
+    }
+
+    // The counts are all 0 once the cache's slot has been purged.
+    pub fn hits(&self) -> u64 {
+        self.with_entries(|entries| entries.hits).unwrap_or(0)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.with_entries(|entries| entries.misses).unwrap_or(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.with_entries(|entries| entries.entries.len())
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        let _ = self.with_entries(|entries| entries.entries.clear());
+    }
+}
+
+pub fn use_memo_cache<K, V>(capacity: usize) -> MemoCache<K, V>
+where
+    K: 'static + Hash + Eq + Clone,
+    V: 'static + Clone,
+{
+    let hook_id = next_hook_id();
+    let store = current_store().unwrap();
+    let mut store_mut = store.borrow_mut();
+    let resized = store_mut
+        .with_state_mut(hook_id, |entries: &mut CacheEntries<K, V>| {
+            if entries.capacity != capacity {
+                entries.set_capacity(capacity)
+            }
+        })
+        .is_ok();
+    if !resized {
+        store_mut.set_state_with_topo_id(CacheEntries::<K, V>::new(capacity), hook_id);
+    }
+    MemoCache {
+        id: hook_id,
+        store: StoreHandle::current(),
+        _phantom_data: PhantomData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init_root_context;
+    use crate::testing::{Counter, TestRenderer};
+
+    #[test]
+    fn memo_deps_recompute_only_when_deps_change() {
+        init_root_context();
+        let computed = Counter::new();
+        let render = |deps: (u32, &'static str)| {
+            topo::root!(use_memo_deps(deps, |(count, label)| {
+                computed.tick();
+                format!("{} {}", count, label)
+            }))
+        };
+
+        assert_eq!(render((1, "item")), "1 item");
+        assert_eq!(render((1, "item")), "1 item");
+        assert_eq!(computed.count(), 1);
+        assert_eq!(render((2, "items")), "2 items");
+        assert_eq!(computed.count(), 2);
+    }
+
+    #[test]
+    fn memo_cache_keeps_the_most_recently_used_results() {
+        init_root_context();
+        let computed = Counter::new();
+        let render = |filter: &'static str| {
+            topo::root!({
+                let cache = use_memo_cache::<&str, String>(2);
+                let value = cache.get_or_compute(filter, || {
+                    computed.tick();
+                    filter.to_uppercase()
+                });
+                (value, cache)
+            })
+        };
+
+        render("all");
+        render("done");
+        render("all");
+        assert_eq!(computed.count(), 2);
+        render("todo");
+        let (value, cache) = render("all");
+        assert_eq!(value, "ALL");
+        assert_eq!(computed.count(), 3);
+        render("done");
+        assert_eq!(computed.count(), 4);
+        assert_eq!((cache.hits(), cache.misses(), cache.len()), (2, 4, 2));
+    }
+
+    #[test]
+    fn memo_cache_computes_once_its_slot_is_purged() {
+        let mut renderer = TestRenderer::new(|| use_memo_cache::<u32, u32>(2));
+        let cache = renderer.render();
+        assert_eq!(cache.get_or_compute(1, || 10), 10);
+
+        renderer.unmount();
+        assert_eq!(cache.get_or_compute(1, || 20), 20);
+        assert_eq!((cache.hits(), cache.misses(), cache.len()), (0, 0, 0));
+        cache.clear();
+    }
+
+    #[test]
+    fn previous_values_and_keyed_watches() {
+        #[derive(Clone)]
+        struct Doc {
+            version: u32,
+            body: String,
+        }
+
+        init_root_context();
+        let render = |doc: Doc| {
+            topo::root!((
+                use_previous(&doc.version),
+                watch_key(&doc, |doc| doc.version).changed,
+                watch_by(&doc, |watched, current| watched.body.len()
+                    == current.body.len())
+                .changed,
+            ))
+        };
+        let doc = |version, body: &str| Doc {
+            version,
+            body: body.to_string(),
+        };
+
+        assert_eq!(render(doc(1, "a")), (None, false, false));
+        assert_eq!(render(doc(1, "b")), (Some(1), false, false));
+        assert_eq!(render(doc(2, "bb")), (Some(1), true, true));
+        assert_eq!(render(doc(2, "bb")), (Some(2), false, false));
+    }
+}
@@ -0,0 +1,59 @@
+// A pluggable callback fired whenever stored state changes outside of a render,
+// e.g. via StateAccess::set in an event handler, timer, fetch callback or mailbox.
+// Integrations such as seed_comp_helpers use it to schedule a redraw.
+use crate::store::{current_store, Store};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Clone)]
+pub struct StateChangedNotifier(Rc<dyn Fn(topo::Id)>);
+
+impl fmt::Debug for StateChangedNotifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("StateChangedNotifier")
+    }
+}
+
+pub fn set_state_changed_notifier<F: Fn(topo::Id) + 'static>(func: F) {
+    let store = current_store();
+    store.unwrap().borrow_mut().notifier = Some(StateChangedNotifier(Rc::new(func)));
+}
+
+pub fn clear_state_changed_notifier() {
+    let store = current_store();
+    store.unwrap().borrow_mut().notifier = None;
+}
+
+// Must not be called while the store is borrowed.
+// Within a transaction the id is notified when the transaction is committed instead.
+pub fn notify_state_changed(store: &RefCell<Store>, id: topo::Id) {
+    if store.borrow_mut().defer_notify(id) {
+        return;
+    }
+    let notifier = store.borrow().notifier.clone();
+    if let Some(notifier) = notifier {
+        (notifier.0)(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Counter;
+    use crate::{init_root_context, use_state};
+
+    #[test]
+    fn changes_but_not_initialisation_are_notified() {
+        init_root_context();
+        let notified = Counter::new();
+        let counter = notified.clone();
+        set_state_changed_notifier(move |_id| counter.tick());
+
+        let (_, access) = topo::root!(use_state(|| 1));
+        assert_eq!(notified.count(), 0);
+        access.set(2);
+        access.update(|value| *value += 1);
+        assert_eq!(notified.count(), 2);
+    }
+}
@@ -0,0 +1,297 @@
+// Hook state that outlives the page, loaded from a StorageBackend on first render and
+// saved back whenever it changes, whether through its PersistedStateAccess or otherwise,
+// e.g. by undo, a rolled back transaction or a lens.
+//
+// Values are stored as json alongside a version number. Bump the version when the type
+// changes shape and pass a migration to turn older payloads into the current type.
+//
+// set_storage_backend(seed_comp_helpers::storage::LocalStorage);
+// let (todos, todos_access) = use_persisted_state("todos", || Vec::<Todo>::new());
+// todos_access.update(|todos| todos.push(todo));
+use crate::store::{current_store, next_hook_id, StateAccess, StateError, Store};
+use serde_crate::de::DeserializeOwned;
+use serde_crate::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+pub trait StorageBackend {
+    fn load(&self, key: &str) -> Option<String>;
+    fn save(&self, key: &str, value: &str);
+    fn remove(&self, key: &str);
+}
+
+// Keeps values for as long as it, or a clone of it, is alive.
+// Clones share their values, so a test can render with a fresh Store over the same
+// MemoryStorage to check what survives a reload.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage(Rc<RefCell<HashMap<String, String>>>);
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+}
+
+impl StorageBackend for MemoryStorage {
+    fn load(&self, key: &str) -> Option<String> {
+        self.0.borrow().get(key).cloned()
+    }
+
+    fn save(&self, key: &str, value: &str) {
+        self.0
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    fn remove(&self, key: &str) {
+        self.0.borrow_mut().remove(key);
+    }
+}
+
+#[derive(Clone)]
+pub struct StorageHandle(Rc<dyn StorageBackend>);
+
+impl fmt::Debug for StorageHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("StorageHandle")
+    }
+}
+
+pub fn set_storage_backend<B: StorageBackend + 'static>(backend: B) {
+    let store = current_store();
+    store.unwrap().borrow_mut().storage = Some(StorageHandle(Rc::new(backend)));
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "serde_crate")]
+struct Payload<T> {
+    version: u32,
+    value: T,
+}
+
+pub type Migration<T> = fn(version: u32, value: serde_json::Value) -> Option<T>;
+
+// Where and how a persisted slot is saved.
+#[derive(Clone, Debug)]
+pub struct PersistedSlot {
+    key: String,
+    version: u32,
+    to_json: fn(&Store, topo::Id, u32) -> Option<String>,
+}
+
+fn slot_json<T: 'static + Serialize>(store: &Store, id: topo::Id, version: u32) -> Option<String> {
+    store
+        .peek_state_with_topo_id::<T>(id)
+        .map(|value| to_json(&Payload { version, value }))
+}
+
+impl Store {
+    // Called by bump_version, so every change to a persisted slot is saved. Does nothing
+    // while the value is lent out, the change is saved once it is put back.
+    pub(crate) fn save_persisted(&self, id: topo::Id) {
+        if let (Some(slot), Some(storage)) = (self.persisted.get(&id), &self.storage) {
+            if let Some(json) = (slot.to_json)(self, id, slot.version) {
+                storage.0.save(&slot.key, &json);
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PersistedStateAccess<T> {
+    key: String,
+    storage: StorageHandle,
+    state_access: StateAccess<T>,
+}
+
+impl<T> PersistedStateAccess<T>
+where
+    T: 'static + Clone + Serialize,
+{
+    pub fn try_set(&self, value: T) -> Result<(), StateError> {
+        self.state_access.try_set(value)
+    }
+
+    pub fn try_update<F: FnOnce(&mut T) -> R, R>(&self, func: F) -> Result<R, StateError> {
+        self.state_access.try_update(func)
+    }
+
+    pub fn try_get(&self) -> Result<T, StateError> {
+        self.state_access.try_get()
+    }
+
+    // Panics where try_set would error.
+    pub fn set(&self, value: T) {
+        self.try_set(value).unwrap();
+    }
+
+    // Panics where try_update would error.
+    pub fn update<F: FnOnce(&mut T)>(&self, func: F) {
+        self.try_update(func).unwrap();
+    }
+
+    pub fn get(&self) -> Option<T> {
+        self.state_access.get()
+    }
+
+    // Removes the saved value, the current state is left as it is until it next changes.
+    pub fn forget(&self) {
+        (self.storage.0).remove(&self.key);
+    }
+}
+
+fn to_json<T: Serialize>(payload: &Payload<T>) -> String {
+    serde_json::to_string(payload).expect("persisted state could not be serialized")
+}
+
+// A payload that is missing, unreadable or from another version with no migration
+// falls back to `default`.
+fn load<T: DeserializeOwned>(
+    storage: &StorageHandle,
+    key: &str,
+    version: u32,
+    migrate: Migration<T>,
+) -> Option<T> {
+    let json = storage.0.load(key)?;
+    let payload = serde_json::from_str::<Payload<serde_json::Value>>(&json).ok()?;
+    if payload.version == version {
+        serde_json::from_value(payload.value).ok()
+    } else {
+        migrate(payload.version, payload.value)
+    }
+}
+
+// As use_state but persisted under `key` with the StorageBackend set by set_storage_backend.
+pub fn use_persisted_state<T, F>(key: &str, default: F) -> (T, PersistedStateAccess<T>)
+where
+    T: 'static + Clone + Serialize + DeserializeOwned,
+    F: FnOnce() -> T,
+{
+    use_versioned_persisted_state(key, 0, default, |_, _| None)
+}
+
+// Payloads saved with an older `version` are passed to `migrate`, a migrated value is
+// saved straight back under the current version.
+pub fn use_versioned_persisted_state<T, F>(
+    key: &str,
+    version: u32,
+    default: F,
+    migrate: Migration<T>,
+) -> (T, PersistedStateAccess<T>)
+where
+    T: 'static + Clone + Serialize + DeserializeOwned,
+    F: FnOnce() -> T,
+{
+    let hook_id = next_hook_id();
+    let store = current_store().unwrap();
+    let storage = store
+        .borrow()
+        .storage
+        .clone()
+        .expect("no StorageBackend, has set_storage_backend() been called?");
+    let access = PersistedStateAccess {
+        key: key.to_string(),
+        storage,
+        state_access: StateAccess::new(hook_id),
+    };
+    store.borrow_mut().persisted.insert(
+        hook_id,
+        PersistedSlot {
+            key: key.to_string(),
+            version,
+            to_json: slot_json::<T>,
+        },
+    );
+
+    let existing = store
+        .borrow_mut()
+        .get_state_with_topo_id::<T>(hook_id)
+        .cloned();
+    if let Some(value) = existing {
+        return (value, access);
+    }
+
+    let value = match load(&access.storage, key, version, migrate) {
+        Some(value) => value,
+        None => default(),
+    };
+    // setting the slot saves it, so a migrated value is stored under the current version.
+    store
+        .borrow_mut()
+        .set_state_with_topo_id(value.clone(), hook_id);
+    (value, access)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init_root_context;
+    use crate::testing::TestRenderer;
+
+    #[test]
+    fn persisted_state_survives_a_reload_and_migrates() {
+        let storage = MemoryStorage::new();
+        let reload = || {
+            let storage = storage.clone();
+            TestRenderer::new(move || {
+                set_storage_backend(storage.clone());
+                use_persisted_state("todos", || vec!["write tests".to_string()])
+            })
+        };
+
+        let mut app = reload();
+        let (todos, todos_access) = app.render();
+        assert_eq!(todos, vec!["write tests"]);
+        todos_access.update(|todos| todos.push("ship".to_string()));
+        assert_eq!(reload().render().0, vec!["write tests", "ship"]);
+
+        let mut migrated = TestRenderer::new(move || {
+            set_storage_backend(storage.clone());
+            use_versioned_persisted_state(
+                "todos",
+                1,
+                || 0,
+                |version, value| {
+                    assert_eq!(version, 0);
+                    value.as_array().map(|todos| todos.len())
+                },
+            )
+        });
+        assert_eq!(migrated.render().0, 2);
+    }
+
+    #[test]
+    fn changes_made_without_the_handle_are_saved() {
+        let storage = MemoryStorage::new();
+        let saved = || {
+            load::<u32>(
+                &StorageHandle(Rc::new(storage.clone())),
+                "count",
+                0,
+                |_, _| None,
+            )
+        };
+        init_root_context();
+        set_storage_backend(storage.clone());
+        crate::history::start_recording();
+        let (_, count_access) = topo::root!(use_persisted_state("count", || 1u32));
+
+        count_access.set(2);
+        crate::history::undo();
+        assert_eq!(saved(), Some(1));
+        let rolled_back = crate::try_transaction(|| {
+            count_access.set(3);
+            Err::<(), ()>(())
+        });
+        assert!(rolled_back.is_err());
+        assert_eq!(saved(), Some(1));
+        let store = current_store().unwrap();
+        store
+            .borrow_mut()
+            .with_state_mut(count_access.state_access.id, |count: &mut u32| *count = 4)
+            .unwrap();
+        assert_eq!(saved(), Some(4));
+    }
+}
@@ -0,0 +1,74 @@
+use crate::use_state;
+use std::sync::Arc;
+
+// Sends actions to the reducer of a use_reducer hook.
+// Dispatching goes through StateAccess::update so it is recorded and notified like any set.
+pub struct Dispatcher<A> {
+    dispatch_fn: Arc<dyn Fn(A)>,
+}
+
+impl<A> Clone for Dispatcher<A> {
+    fn clone(&self) -> Self {
+        Dispatcher {
+            dispatch_fn: self.dispatch_fn.clone(),
+        }
+    }
+}
+
+impl<A> Dispatcher<A> {
+    pub fn dispatch(&self, action: A) {
+        (self.dispatch_fn)(action)
+    }
+}
+
+// Elm style state, `reducer` computes the next state from the current state and an action.
+// As the reducer is a plain function it can be tested and replayed without any rendering.
+pub fn use_reducer<S, A, I, R>(init: I, reducer: R) -> (S, Dispatcher<A>)
+where
+    S: 'static + Clone,
+    A: 'static,
+    I: FnOnce() -> S,
+    R: Fn(&S, A) -> S + 'static,
+{
+    let (state, state_access) = use_state(init);
+    let dispatcher = Dispatcher {
+        dispatch_fn: Arc::new(move |action| {
+            state_access.update(|state| {
+                let next_state = reducer(state, action);
+                *state = next_state;
+            })
+        }),
+    };
+    (state, dispatcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init_root_context;
+
+    #[test]
+    fn dispatched_actions_are_reduced_into_state() {
+        enum Action {
+            Add(i32),
+            Reset,
+        }
+        fn reducer(total: &i32, action: Action) -> i32 {
+            match action {
+                Action::Add(amount) => total + amount,
+                Action::Reset => 0,
+            }
+        }
+
+        init_root_context();
+        let render = || topo::root!(use_reducer(|| 0, reducer));
+
+        let (total, dispatcher) = render();
+        assert_eq!(total, 0);
+        dispatcher.dispatch(Action::Add(3));
+        dispatcher.dispatch(Action::Add(4));
+        assert_eq!(render().0, 7);
+        dispatcher.dispatch(Action::Reset);
+        assert_eq!(render().0, 0);
+    }
+}
@@ -0,0 +1,113 @@
+// Derived state that keeps track of its own inputs. Every slot read from the Store while
+// `func` runs is recorded along with its version, and the value is only recomputed once
+// one of those slots has been written to since.
+//
+// let done_count = use_selector(|| {
+//     list_control.get_list().items().filter(|item| item.completed).count()
+// });
+//
+// Only state read from the Store is tracked, values captured from the surrounding scope are not.
+use crate::store::{current_store, next_hook_id, Store};
+use std::collections::HashSet;
+
+#[derive(Clone)]
+struct Selection<T> {
+    value: T,
+    reads: Vec<(topo::Id, Option<u64>)>,
+}
+
+impl Store {
+    pub fn start_tracking_reads(&mut self) {
+        self.read_trackers.push(HashSet::new());
+    }
+
+    // The reads also count towards any selector that is computing this one.
+    pub fn stop_tracking_reads(&mut self) -> HashSet<topo::Id> {
+        let reads = self.read_trackers.pop().unwrap_or_default();
+        if let Some(outer_reads) = self.read_trackers.last_mut() {
+            outer_reads.extend(reads.iter().cloned());
+        }
+        reads
+    }
+}
+
+pub fn use_selector<T: 'static + Clone, F: FnOnce() -> T>(func: F) -> T {
+    let hook_id = next_hook_id();
+    let store = current_store().unwrap();
+    {
+        let mut store_mut = store.borrow_mut();
+        let selection = store_mut
+            .get_state_with_topo_id::<Selection<T>>(hook_id)
+            .cloned();
+        if let Some(selection) = selection {
+            let unchanged = selection
+                .reads
+                .iter()
+                .all(|(id, version)| store_mut.version(*id) == *version);
+            if unchanged {
+                // the inputs are still read, as far as purging and enclosing selectors go.
+                for (id, _) in &selection.reads {
+                    store_mut.mark_read(*id);
+                }
+                return selection.value;
+            }
+        }
+        store_mut.start_tracking_reads();
+    }
+
+    let value = func();
+
+    let mut store_mut = store.borrow_mut();
+    let reads = store_mut
+        .stop_tracking_reads()
+        .into_iter()
+        .map(|id| (id, store_mut.version(id)))
+        .collect();
+    store_mut.set_state_with_topo_id(
+        Selection {
+            value: value.clone(),
+            reads,
+        },
+        hook_id,
+    );
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Counter, TestRenderer};
+    use crate::use_state;
+
+    #[test]
+    fn selectors_recompute_only_when_state_they_read_changes() {
+        let computed = Counter::new();
+        let mut renderer = {
+            let computed = computed.clone();
+            TestRenderer::new(move || {
+                let (_, price_access) = use_state(|| 2);
+                let (_, quantity_access) = use_state(|| 3);
+                let (_, unread_access) = use_state(|| "unread");
+                let computed = computed.clone();
+                let (price, quantity) = (price_access.clone(), quantity_access.clone());
+                let total = use_selector(move || {
+                    computed.tick();
+                    price.hard_get() * quantity.hard_get()
+                });
+                (total, price_access, unread_access)
+            })
+        };
+
+        let (total, price_access, unread_access) = renderer.render();
+        assert_eq!((total, computed.count()), (6, 1));
+        assert_eq!(renderer.rerender().0, 6);
+        unread_access.set("changed");
+        assert_eq!(renderer.rerender().0, 6);
+        assert_eq!(computed.count(), 1);
+
+        price_access.set(5);
+        assert_eq!(renderer.rerender().0, 15);
+        assert_eq!(renderer.rerender().0, 15);
+        assert_eq!(computed.count(), 2);
+    }
+}
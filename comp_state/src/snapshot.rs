@@ -0,0 +1,277 @@
+// Snapshots of the whole Store, used for persisting state across page reloads
+// and for attaching state dumps to bug reports.
+//
+// Values are type erased inside the Store so only types that have been registered
+// (under a stable name of the caller's choosing) are written to a snapshot.
+use crate::history::History;
+use crate::notify::notify_state_changed;
+use crate::store::{current_store, Store};
+use serde_crate::de::DeserializeOwned;
+use serde_crate::{Deserialize, Serialize};
+use slotmap::{DefaultKey, SecondaryMap};
+use std::fmt;
+
+// Each value is kept as json text so the snapshot itself can be written out
+// with any serde format, e.g. json or bincode.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "serde_crate")]
+pub struct StoreSnapshot {
+    pub ids: Vec<u64>,
+    pub states: Vec<StateSnapshot>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "serde_crate")]
+pub struct StateSnapshot {
+    pub type_name: String,
+    pub id: u64,
+    pub value: String,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    UnregisteredType(String),
+    Serde(serde_json::Error),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::UnregisteredType(type_name) => {
+                write!(f, "no state type registered as '{}'", type_name)
+            }
+            SnapshotError::Serde(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<serde_json::Error> for SnapshotError {
+    fn from(err: serde_json::Error) -> SnapshotError {
+        SnapshotError::Serde(err)
+    }
+}
+
+type SnapshotFn = fn(&Store) -> Result<Vec<(topo::Id, String)>, serde_json::Error>;
+type RestoreFn = fn(&mut Store, topo::Id, &str) -> Result<(), serde_json::Error>;
+
+// The typed functions that move one registered state type in and out of a snapshot.
+#[derive(Clone, Copy, Debug)]
+pub struct StateSerializer {
+    snapshot: SnapshotFn,
+    restore: RestoreFn,
+}
+
+fn snapshot_values<T: Serialize + 'static>(
+    store: &Store,
+) -> Result<Vec<(topo::Id, String)>, serde_json::Error> {
+    let mut values = vec![];
+    if let Some(sec_map) = store.anymap.get::<SecondaryMap<DefaultKey, T>>() {
+        for (key, value) in sec_map.iter() {
+            // secondary maps can hold values for purged keys, skip those.
+            if let Some(id) = store.primary_slotmap.get(key) {
+                values.push((*id, serde_json::to_string(value)?));
+            }
+        }
+    }
+    Ok(values)
+}
+
+fn restore_value<T: DeserializeOwned + 'static>(
+    store: &mut Store,
+    id: topo::Id,
+    value: &str,
+) -> Result<(), serde_json::Error> {
+    let value = serde_json::from_str::<T>(value)?;
+    store.set_state_with_topo_id(value, id);
+    Ok(())
+}
+
+impl Store {
+    pub fn register_serializable<T: Serialize + DeserializeOwned + 'static>(
+        &mut self,
+        type_name: &str,
+    ) {
+        self.serializers.insert(
+            type_name.to_string(),
+            StateSerializer {
+                snapshot: snapshot_values::<T>,
+                restore: restore_value::<T>,
+            },
+        );
+    }
+
+    pub fn snapshot(&self) -> Result<StoreSnapshot, SnapshotError> {
+        let mut ids = self.id_to_key_map.keys().map(|id| id.0).collect::<Vec<_>>();
+        ids.sort();
+
+        let mut states = vec![];
+        for (type_name, serializer) in &self.serializers {
+            for (id, value) in (serializer.snapshot)(self)? {
+                states.push(StateSnapshot {
+                    type_name: type_name.clone(),
+                    id: id.0,
+                    value,
+                });
+            }
+        }
+        // sorted so that two snapshots of the same state are identical.
+        states.sort_by(|a, b| (&a.type_name, a.id).cmp(&(&b.type_name, b.id)));
+
+        Ok(StoreSnapshot { ids, states })
+    }
+
+    // Replaces all state in the store with the snapshot's state, everything else such as
+    // the notifier and effect cleanups is kept.
+    // The store is left untouched if any value fails to restore.
+    pub fn restore(&mut self, snapshot: &StoreSnapshot) -> Result<(), SnapshotError> {
+        let mut restored = Store::new();
+        restored.serializers = self.serializers.clone();
+        restored.storage = self.storage.clone();
+        restored.persisted = self.persisted.clone();
+        // keep the clock running so restored slots never repeat a version a selector has seen.
+        restored.version_clock = self.version_clock;
+
+        for id in &snapshot.ids {
+            let id = topo::Id(*id);
+            let key = restored.primary_slotmap.insert(id);
+            restored.id_to_key_map.insert(id, key);
+        }
+
+        for state in &snapshot.states {
+            let serializer = restored
+                .serializers
+                .get(&state.type_name)
+                .copied()
+                .ok_or_else(|| SnapshotError::UnregisteredType(state.type_name.clone()))?;
+            (serializer.restore)(&mut restored, topo::Id(state.id), &state.value)?;
+        }
+
+        self.id_to_key_map = restored.id_to_key_map;
+        self.primary_slotmap = restored.primary_slotmap;
+        self.anymap = restored.anymap;
+        self.versions = restored.versions;
+        self.version_clock = restored.version_clock;
+        // recorded changes do not apply to the restored state, so start afresh.
+        if self.is_recording() {
+            self.history = Some(History::default());
+        }
+        Ok(())
+    }
+}
+
+pub fn register_serializable_state<T: Serialize + DeserializeOwned + 'static>(type_name: &str) {
+    let store = current_store();
+    store
+        .unwrap()
+        .borrow_mut()
+        .register_serializable::<T>(type_name);
+}
+
+pub fn snapshot_store() -> Result<StoreSnapshot, SnapshotError> {
+    let store = current_store();
+    store.unwrap().borrow().snapshot()
+}
+
+// Effects whose slots are not in the snapshot are cleaned up, as their state is gone and
+// no purge would find them. Those in it are cleaned up when next purged or rerun.
+pub fn restore_store(snapshot: &StoreSnapshot) -> Result<(), SnapshotError> {
+    let store = current_store().unwrap();
+    let cleanups = {
+        let mut store_mut = store.borrow_mut();
+        store_mut.restore(snapshot)?;
+        let gone = store_mut
+            .effect_cleanups
+            .keys()
+            .filter(|id| !store_mut.id_to_key_map.contains_key(id))
+            .cloned()
+            .collect::<Vec<_>>();
+        gone.iter()
+            .filter_map(|id| store_mut.effect_cleanups.remove(id))
+            .collect::<Vec<_>>()
+    };
+    for cleanup in cleanups {
+        cleanup.run();
+    }
+    for state in &snapshot.states {
+        notify_state_changed(&store, topo::Id(state.id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Counter, Log};
+    use crate::{
+        init_root_context, purge_and_reset_unseen_ids, set_state_changed_notifier, use_effect,
+        use_state,
+    };
+
+    #[test]
+    fn store_snapshot_round_trips() {
+        let mut store = Store::new();
+        store.register_serializable::<i32>("i32");
+        store.register_serializable::<String>("string");
+        store.set_state_with_topo_id(42, topo::Id(1));
+        store.set_state_with_topo_id("hello".to_string(), topo::Id(2));
+
+        let json = serde_json::to_string(&store.snapshot().unwrap()).unwrap();
+
+        let mut restored = Store::new();
+        restored.register_serializable::<i32>("i32");
+        restored.register_serializable::<String>("string");
+        restored
+            .restore(&serde_json::from_str(&json).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            restored.get_state_with_topo_id::<i32>(topo::Id(1)),
+            Some(&42)
+        );
+        assert_eq!(
+            restored.get_state_with_topo_id::<String>(topo::Id(2)),
+            Some(&"hello".to_string())
+        );
+        assert_eq!(restored.snapshot().unwrap(), store.snapshot().unwrap());
+    }
+
+    #[test]
+    fn restoring_keeps_the_notifier() {
+        init_root_context();
+        register_serializable_state::<i32>("i32");
+        let notified = Counter::new();
+        let counter = notified.clone();
+        set_state_changed_notifier(move |_id| counter.tick());
+        let (_, access) = topo::root!(use_state(|| 1));
+
+        restore_store(&snapshot_store().unwrap()).unwrap();
+        assert_eq!(notified.count(), 1);
+        access.set(5);
+        assert_eq!(notified.count(), 2);
+        assert_eq!(access.get(), Some(5));
+    }
+
+    #[test]
+    fn restoring_runs_or_keeps_effect_cleanups() {
+        init_root_context();
+        let log = Log::new();
+        let effect = |deps: u32| {
+            let log = log.clone();
+            topo::root!(use_effect(deps, move || move || log.push(format!("cleanup {}", deps))));
+        };
+
+        let before_effect = snapshot_store().unwrap();
+        effect(1);
+        restore_store(&before_effect).unwrap();
+        assert_eq!(log.events(), vec!["cleanup 1"]);
+
+        effect(2);
+        restore_store(&snapshot_store().unwrap()).unwrap();
+        assert_eq!(log.events(), vec!["cleanup 1"]);
+        purge_and_reset_unseen_ids();
+        purge_and_reset_unseen_ids();
+        assert_eq!(log.events(), vec!["cleanup 1", "cleanup 2"]);
+    }
+}
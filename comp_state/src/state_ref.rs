@@ -0,0 +1,114 @@
+// Hook state for values that are not Clone, such as web_sys closures or large buffers.
+// Rather than copying values out of the store, a StateRef lends them to a closure.
+//
+// The value is lent to the closures as StateAccess::update lends it, so they are free to
+// access other state but not the StateRef's own state.
+// Changes made through a StateRef are notified but cannot be recorded in history,
+// as history needs to keep a copy of the old value.
+use crate::notify::notify_state_changed;
+use crate::store::{current_store, next_hook_id, with_state_taken, StateError, StoreHandle};
+use std::fmt;
+use std::marker::PhantomData;
+
+pub struct StateRef<T> {
+    pub id: topo::Id,
+    store: StoreHandle,
+    _phantom_data: PhantomData<T>,
+}
+
+// Clone whatever T is, sharing a value that is not Clone is what StateRef is for.
+impl<T> Clone for StateRef<T> {
+    fn clone(&self) -> Self {
+        StateRef {
+            id: self.id,
+            store: self.store.clone(),
+            _phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for StateRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StateRef").field("id", &self.id).finish()
+    }
+}
+
+impl<T> StateRef<T> {
+    pub fn new(id: topo::Id) -> StateRef<T> {
+        StateRef {
+            id,
+            store: StoreHandle::current(),
+            _phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<T> StateRef<T>
+where
+    T: 'static,
+{
+    pub fn try_with<F: FnOnce(&T) -> R, R>(&self, func: F) -> Result<R, StateError> {
+        let store = self.store.get()?;
+        with_state_taken(&store, self.id, |state| func(state))
+    }
+
+    pub fn try_with_mut<F: FnOnce(&mut T) -> R, R>(&self, func: F) -> Result<R, StateError> {
+        let store = self.store.get()?;
+        let result = with_state_taken(&store, self.id, func)?;
+        store.borrow_mut().bump_version(self.id);
+        notify_state_changed(&store, self.id);
+        Ok(result)
+    }
+
+    pub fn try_set(&self, value: T) -> Result<(), StateError> {
+        self.try_with_mut(|state| *state = value)
+    }
+
+    // Panics where try_with would error.
+    pub fn with<F: FnOnce(&T) -> R, R>(&self, func: F) -> R {
+        self.try_with(func).unwrap()
+    }
+
+    // Panics where try_with_mut would error.
+    pub fn with_mut<F: FnOnce(&mut T) -> R, R>(&self, func: F) -> R {
+        self.try_with_mut(func).unwrap()
+    }
+
+    // Panics where try_set would error.
+    pub fn set(&self, value: T) {
+        self.try_set(value).unwrap()
+    }
+}
+
+pub fn use_state_ref<T: 'static, F: FnOnce() -> T>(data_fn: F) -> StateRef<T> {
+    let hook_id = next_hook_id();
+    let store = current_store();
+    let store = store.unwrap();
+
+    let exists = store
+        .borrow_mut()
+        .get_state_with_topo_id::<T>(hook_id)
+        .is_some();
+    if !exists {
+        let data = data_fn();
+        store.borrow_mut().set_state_with_topo_id(data, hook_id);
+    }
+    StateRef::new(hook_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init_root_context;
+
+    #[test]
+    fn state_refs_hold_values_that_are_not_clone() {
+        struct Buffer(Vec<u8>);
+
+        init_root_context();
+        let render = || topo::root!(use_state_ref(|| Buffer(vec![1, 2])));
+
+        render().with_mut(|buffer| buffer.0.push(3));
+        assert_eq!(render().with(|buffer| buffer.0.len()), 3);
+    }
+}
@@ -1,16 +1,77 @@
+use crate::effect::EffectCleanup;
+use crate::frame::KeepAlive;
+use crate::history::{History, ValueFns};
+use crate::lens::Lens;
+use crate::notify::{notify_state_changed, StateChangedNotifier};
+use crate::transaction::Transaction;
 use anymap::any::Any;
 use slotmap::{DefaultKey, Key, SecondaryMap, SlotMap};
+use std::any::TypeId;
 use std::cell::RefCell;
 use std::collections::HashMap;
 pub use std::collections::HashSet;
+use std::fmt;
 use std::marker::PhantomData;
+use std::rc::{Rc, Weak};
 use std::sync::Arc;
 use topo::*;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateError {
+    // init_root_context() has not been called so there is no Store in the topo Env,
+    // or the Store the access was created in has since been dropped.
+    NoStore,
+    // Nothing is stored for the id, it was never set or has been purged.
+    Missing,
+    // The id has state but none of the requested type.
+    TypeMismatch,
+    // The value is out of its slot while an update closure runs, e.g. the closure
+    // passed to StateAccess::update tried to access the state it is updating.
+    InUse,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::NoStore => f.write_str("no Store, has init_root_context() been called?"),
+            StateError::Missing => f.write_str("no state stored for this topo::Id"),
+            StateError::TypeMismatch => {
+                f.write_str("no state of this type stored for this topo::Id")
+            }
+            StateError::InUse => f.write_str("the state for this topo::Id is being updated"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+// The Store a StateAccess or StateRef was created in, so that it reaches the same Store
+// when used from an event handler outside of any with_store scope.
+// Weak as accesses are themselves kept in the Store, e.g. by use_two_way.
+// None if created where there was no Store, the current Store is then used.
+#[derive(Clone, Debug)]
+pub(crate) struct StoreHandle(Option<Weak<RefCell<Store>>>);
+
+impl StoreHandle {
+    pub(crate) fn current() -> StoreHandle {
+        StoreHandle(current_store().map(|store| Rc::downgrade(&store)))
+    }
+
+    pub(crate) fn get(&self) -> Result<Rc<RefCell<Store>>, StateError> {
+        match &self.0 {
+            Some(store) => store.upgrade().ok_or(StateError::NoStore),
+            None => current_store().ok_or(StateError::NoStore),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct StateAccess<T> {
     pub id: topo::Id,
-    _phantom_data: PhantomData<T>,
+    pub(crate) store: StoreHandle,
+    // set when the access has been mapped onto part of the slot's value.
+    pub(crate) lens: Option<Lens<T>>,
+    pub(crate) _phantom_data: PhantomData<T>,
 }
 
 impl<T> StateAccess<T>
@@ -18,87 +79,296 @@ where
     T: 'static + Clone,
 {
     pub fn new(id: topo::Id) -> StateAccess<T> {
+        let store = StoreHandle::current();
+        if let Ok(store) = store.get() {
+            store.borrow_mut().register_value_fns::<T>();
+        }
         StateAccess {
             id,
+            store,
+            lens: None,
             _phantom_data: PhantomData,
         }
     }
 
-    pub fn set(&self, value: T) {
-        set_state_with_topo_id(value, self.id);
+    // The try_ methods never panic, use them where the state may have been purged,
+    // e.g. in an event handler that outlives its component.
+    pub fn try_set(&self, value: T) -> Result<(), StateError> {
+        let store = self.store.get()?;
+        match &self.lens {
+            Some(lens) => lens.try_update(&store, self.id, |state| *state = value),
+            None => try_set_state_in(&store, value, self.id),
+        }
     }
 
-    pub fn update<F: FnOnce(&mut T) -> ()>(&self, func: F) {
-        let item = &mut self.get().unwrap();
-        func(item);
-        self.set(item.clone());
+    pub fn try_get(&self) -> Result<T, StateError> {
+        let store = self.store.get()?;
+        match &self.lens {
+            Some(lens) => lens.try_get(&store, self.id),
+            None => try_get_state_in::<T>(&store, self.id),
+        }
+    }
+
+    // Panics where try_set would error.
+    pub fn set(&self, value: T) {
+        self.try_set(value).unwrap();
     }
 
+    // None where try_get would be Missing or TypeMismatch, panics if there is no Store or
+    // if called from within an update of this state (InUse).
     pub fn get(&self) -> Option<T> {
-        get_state_with_topo_id::<T>(self.id)
+        match self.try_get() {
+            Ok(state) => Some(state),
+            Err(StateError::Missing) | Err(StateError::TypeMismatch) => None,
+            Err(err) => panic!("{}", err),
+        }
     }
 
+    // Panics where try_get would error.
     pub fn hard_get(&self) -> T {
-        get_state_with_topo_id::<T>(self.id).unwrap()
+        self.try_get().unwrap()
+    }
+}
+
+impl<T> StateAccess<T>
+where
+    T: 'static,
+{
+    // `func` is lent the value in place, without copying it. The value is out of its slot
+    // while `func` runs, so `func` is free to read and set other state but accessing this
+    // state from within it gives StateError::InUse.
+    pub fn try_update<F: FnOnce(&mut T) -> R, R>(&self, func: F) -> Result<R, StateError> {
+        let store = self.store.get()?;
+        match &self.lens {
+            Some(lens) => lens.try_update(&store, self.id, func),
+            None => try_update_state_in(&store, self.id, func),
+        }
+    }
+
+    // Panics where try_update would error, as it would for StateError::InUse.
+    pub fn update<F: FnOnce(&mut T) -> ()>(&self, func: F) {
+        self.try_update(func).unwrap();
+    }
+
+    // Lends the value to `func` as try_update does, for reading part of it without
+    // copying the whole value out.
+    pub fn try_with<F: FnOnce(&T) -> R, R>(&self, func: F) -> Result<R, StateError> {
+        let store = self.store.get()?;
+        match &self.lens {
+            Some(lens) => lens.try_with(&store, self.id, func),
+            None => with_state_taken(&store, self.id, |state| func(state)),
+        }
+    }
+
+    // Panics where try_with would error.
+    pub fn with<F: FnOnce(&T) -> R, R>(&self, func: F) -> R {
+        self.try_with(func).unwrap()
     }
 }
 
+// The Store for the current scope, the one entered by with_store or else the one
+// added by init_root_context.
+pub fn current_store() -> Option<Rc<RefCell<Store>>> {
+    topo::Env::get::<Rc<RefCell<Store>>>().map(|store| Rc::clone(&store))
+}
+
+pub fn new_store() -> Rc<RefCell<Store>> {
+    Rc::new(RefCell::new(Store::new()))
+}
+
+// Runs `func` with its own Store, e.g. one per app on a page, per test, or private to a
+// library's components. All state used within `func`, including by any StateAccess or
+// StateRef created there, lives in `store` rather than the outer Store.
+// `func` runs as a topo call so the Store is only in scope for it.
+pub fn with_store<R, F: FnOnce() -> R>(store: &Rc<RefCell<Store>>, func: F) -> R {
+    topo::call!(
+        func(),
+        env! {
+            Rc<RefCell<Store>> => Rc::clone(store),
+        }
+    )
+}
+
+// clone_state and state_getter read the state set_state wrote for the current component.
+// They do not see state from use_state, which lives in the component's hook slots.
 pub fn clone_state<T: 'static + Clone>() -> Option<T> {
-    let store = topo::Env::get::<RefCell<Store>>();
+    let store = current_store();
     store.unwrap().borrow_mut().get_state::<T>().cloned()
 }
 
 pub fn init_root_context() {
-    if topo::Env::get::<RefCell<Store>>().is_none() {
-        topo::Env::add(RefCell::new(Store::default()));
+    if current_store().is_none() {
+        topo::Env::add(new_store());
     }
 }
 
+// set_state writes to the component currently being rendered, so it is treated as part of
+// that render rather than as a change to be recorded in history or notified.
 pub fn set_state<T: 'static + Clone>(data: T) {
     let current_id = topo::Id::current();
-    assert!(topo::Env::get::<RefCell<Store>>().is_some());
+    assert!(current_store().is_some());
 
-    let store = topo::Env::get::<RefCell<Store>>();
+    let store = current_store();
     store
         .unwrap()
         .borrow_mut()
         .set_state_with_topo_id::<T>(data, current_id);
 }
 
-pub fn set_state_with_topo_id<T: 'static + Clone>(data: T, current_id: topo::Id) {
-    let store = topo::Env::get::<RefCell<Store>>();
-    store
-        .unwrap()
-        .borrow_mut()
-        .set_state_with_topo_id::<T>(data, current_id);
+// Initialising a slot is not a change, replacing an existing value is and will be
+// recorded (if recording) and passed on to any state changed notifier.
+// Errors only with NoStore.
+pub fn try_set_state_with_topo_id<T: 'static + Clone>(
+    data: T,
+    current_id: topo::Id,
+) -> Result<(), StateError> {
+    let store = current_store().ok_or(StateError::NoStore)?;
+    try_set_state_in(&store, data, current_id)
 }
 
-pub fn get_state_with_topo_id<T: 'static + Clone>(id: topo::Id) -> Option<T> {
-    let store = topo::Env::get::<RefCell<Store>>();
-    store
-        .unwrap()
+fn try_set_state_in<T: 'static + Clone>(
+    store: &RefCell<Store>,
+    data: T,
+    current_id: topo::Id,
+) -> Result<(), StateError> {
+    let changed = {
+        let mut store_mut = store.borrow_mut();
+        if store_mut.taken_ids.contains(&current_id) {
+            return Err(StateError::InUse);
+        }
+        store_mut.register_value_fns::<T>();
+
+        let changed = store_mut.contains_state_with_topo_id::<T>(current_id);
+        store_mut.save_for_rollback::<T>(current_id);
+        store_mut.record_set(&data, current_id);
+        store_mut.set_state_with_topo_id::<T>(data, current_id);
+        changed
+    };
+    if changed {
+        notify_state_changed(store, current_id);
+    }
+    Ok(())
+}
+
+pub fn try_get_state_with_topo_id<T: 'static + Clone>(id: topo::Id) -> Result<T, StateError> {
+    let store = current_store().ok_or(StateError::NoStore)?;
+    try_get_state_in(&store, id)
+}
+
+#[allow(clippy::map_clone)]
+fn try_get_state_in<T: 'static + Clone>(
+    store: &RefCell<Store>,
+    id: topo::Id,
+) -> Result<T, StateError> {
+    let state = store
         .borrow_mut()
-        .get_state_with_topo_id::<T>(id)
-        .cloned()
+        .try_get_state_with_topo_id::<T>(id)
+        .map(|state| state.clone());
+    state
+}
+
+// Mutates the stored value in place, as StateAccess::try_update. The old value is only
+// copied if history is being recorded.
+pub fn try_update_state_with_topo_id<T: 'static, F: FnOnce(&mut T) -> R, R>(
+    id: topo::Id,
+    func: F,
+) -> Result<R, StateError> {
+    let store = current_store().ok_or(StateError::NoStore)?;
+    try_update_state_in(&store, id, func)
+}
+
+pub(crate) fn try_update_state_in<T: 'static, F: FnOnce(&mut T) -> R, R>(
+    store: &RefCell<Store>,
+    id: topo::Id,
+    func: F,
+) -> Result<R, StateError> {
+    let old = {
+        let mut store_mut = store.borrow_mut();
+        store_mut.save_for_rollback::<T>(id);
+        if store_mut.is_recording() {
+            store_mut.copy_state::<T>(id)
+        } else {
+            None
+        }
+    };
+    let result = with_state_taken(store, id, func)?;
+    {
+        let mut store_mut = store.borrow_mut();
+        store_mut.bump_version(id);
+        if let (Some(old), Some(new)) = (old, store_mut.copy_state::<T>(id)) {
+            store_mut.record_copied_change::<T>(id, old, new);
+        }
+    }
+    notify_state_changed(store, id);
+    Ok(result)
+}
+
+// A value taken out of its slot, put back when dropped even if the closure it was lent
+// to panics.
+struct TakenState<'a, T: 'static> {
+    store: &'a RefCell<Store>,
+    id: topo::Id,
+    value: Option<T>,
+}
+
+impl<'a, T: 'static> Drop for TakenState<'a, T> {
+    fn drop(&mut self) {
+        if let (Some(value), Ok(mut store_mut)) = (self.value.take(), self.store.try_borrow_mut()) {
+            store_mut.return_state(self.id, value);
+        }
+    }
 }
 
-pub fn update_state_with_topo_id<T: Clone + 'static, F: FnOnce(&mut T) -> ()>(
+// Lends the value in `id`'s slot to `func` with the store released, so that `func` is
+// free to access other state.
+pub(crate) fn with_state_taken<T: 'static, F: FnOnce(&mut T) -> R, R>(
+    store: &RefCell<Store>,
     id: topo::Id,
     func: F,
-) {
-    let item = &mut get_state_with_topo_id::<T>(id).unwrap();
-    func(item);
-    set_state_with_topo_id(item.clone(), id);
+) -> Result<R, StateError> {
+    let value = store.borrow_mut().take_state::<T>(id)?;
+    let mut taken = TakenState {
+        store,
+        id,
+        value: Some(value),
+    };
+    Ok(func(taken.value.as_mut().unwrap()))
+}
+
+// Panics where try_set_state_with_topo_id would error.
+pub fn set_state_with_topo_id<T: 'static + Clone>(data: T, current_id: topo::Id) {
+    try_set_state_with_topo_id(data, current_id).unwrap();
+}
+
+// None where try_get_state_with_topo_id would be Missing or TypeMismatch,
+// panics if there is no Store or the state is being updated (InUse).
+pub fn get_state_with_topo_id<T: 'static + Clone>(id: topo::Id) -> Option<T> {
+    match try_get_state_with_topo_id::<T>(id) {
+        Ok(state) => Some(state),
+        Err(StateError::Missing) | Err(StateError::TypeMismatch) => None,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+// Panics where try_update_state_with_topo_id would error.
+pub fn update_state_with_topo_id<T: 'static, F: FnOnce(&mut T) -> ()>(id: topo::Id, func: F) {
+    try_update_state_with_topo_id(id, func).unwrap();
 }
 //
 
 pub fn reset_unseen_id_list() {
-    let store = topo::Env::get::<RefCell<Store>>();
+    let store = current_store();
     let store = store.unwrap();
     let mut store_mut = store.borrow_mut();
 
     store_mut.unseen_ids = HashSet::new();
-    let ids = store_mut.id_to_key_map.keys().cloned().collect::<Vec<_>>();
+    let ids = store_mut
+        .id_to_key_map
+        .keys()
+        // atoms are app wide and kept alive state outlives its components, neither is unseen.
+        .filter(|id| !store_mut.atom_ids.contains(id) && !store_mut.kept_alive_ids.contains_key(id))
+        .cloned()
+        .collect::<Vec<_>>();
     for id in ids {
         store_mut.unseen_ids.insert(id);
     }
@@ -112,19 +382,31 @@ pub fn reset_unseen_id_list() {
 //     store_mut.unseen_ids.clone()
 // }
 
-fn purge_unseen_ids() {
-    let store = topo::Env::get::<RefCell<Store>>();
+pub fn purge_unseen_ids() {
+    let store = current_store();
     let store = store.unwrap();
-    let mut store_mut = store.borrow_mut();
-
-    let ids = store_mut.unseen_ids.iter().cloned().collect::<Vec<_>>();
-
-    for id in ids {
-        let key = store_mut.id_to_key_map.remove(&id);
-        if let Some(key) = key {
-            store_mut.primary_slotmap.remove(key);
+    let mut cleanups = vec![];
+    {
+        let mut store_mut = store.borrow_mut();
+
+        let ids = store_mut.unseen_ids.iter().cloned().collect::<Vec<_>>();
+
+        for id in ids {
+            store_mut.versions.remove(&id);
+            #[cfg(feature = "serde")]
+            store_mut.persisted.remove(&id);
+            if let Some(cleanup) = store_mut.effect_cleanups.remove(&id) {
+                cleanups.push(cleanup);
+            }
+            let key = store_mut.id_to_key_map.remove(&id);
+            if let Some(key) = key {
+                store_mut.primary_slotmap.remove(key);
+            }
         }
     }
+    for cleanup in cleanups {
+        cleanup.run();
+    }
 }
 
 pub fn purge_and_reset_unseen_ids() {
@@ -132,24 +414,60 @@ pub fn purge_and_reset_unseen_ids() {
     reset_unseen_id_list();
 }
 
+// Claims the next hook slot for the component currently being rendered.
+// Each hook is counted by topo as a call made by the component, so slots are assigned by
+// call order and the count starts again each time the component is rendered.
+// Hooks called outside of any topo call, e.g. not within frame or topo::root!, have no
+// render to be counted within and keep claiming new slots.
+pub fn next_hook_id() -> topo::Id {
+    topo::call!(topo::Id::current())
+}
+
+// Any number of use_state calls, of any type, can be made in one component.
+// Each gets its own slot based on the order the calls are made in.
 pub fn use_state<T: 'static + Clone, F: FnOnce() -> T>(data_fn: F) -> (T, StateAccess<T>) {
-    let current_id = topo::Id::current();
-    if let Some(stored_data) = clone_state::<T>() {
-        (stored_data, StateAccess::new(current_id))
+    let hook_id = next_hook_id();
+    if let Some(stored_data) = get_state_with_topo_id::<T>(hook_id) {
+        (stored_data, StateAccess::new(hook_id))
     } else {
         let data = data_fn();
-        set_state_with_topo_id::<T>(data.clone(), current_id);
-        // get the current topo id context and add the current id
-        (data, StateAccess::new(current_id))
+        set_state_with_topo_id::<T>(data.clone(), hook_id);
+        (data, StateAccess::new(hook_id))
     }
 }
 
+// Writes made through Store methods are neither recorded nor notified, which is what
+// hooks use for their own bookkeeping, e.g. effect deps and memoized values. Hooks only
+// borrow the store for as long as each method call, so the closures they are given are
+// run with it released and are free to access state.
 #[derive(Default, Debug)]
 pub struct Store {
     pub id_to_key_map: HashMap<topo::Id, DefaultKey>,
     pub primary_slotmap: SlotMap<DefaultKey, Id>,
     pub anymap: anymap::Map<dyn Any>,
     pub unseen_ids: HashSet<topo::Id>,
+    #[cfg(feature = "serde")]
+    pub serializers: HashMap<String, crate::snapshot::StateSerializer>,
+    #[cfg(feature = "serde")]
+    pub storage: Option<crate::persist::StorageHandle>,
+    #[cfg(feature = "serde")]
+    pub persisted: HashMap<topo::Id, crate::persist::PersistedSlot>,
+    pub history: Option<History>,
+    pub notifier: Option<StateChangedNotifier>,
+    pub effect_cleanups: HashMap<topo::Id, EffectCleanup>,
+    // every write stamps the slot with the next tick of the clock, so a slot that is
+    // purged and set again never repeats a version.
+    pub versions: HashMap<topo::Id, u64>,
+    pub version_clock: u64,
+    pub read_trackers: Vec<HashSet<topo::Id>>,
+    pub atom_ids: HashSet<topo::Id>,
+    // kept alive ids and the keep_alive call that created them.
+    pub kept_alive_ids: HashMap<topo::Id, topo::Id>,
+    // ids whose values are lent out by with_state_taken.
+    pub taken_ids: HashSet<topo::Id>,
+    pub value_fns: HashMap<TypeId, ValueFns>,
+    pub transaction: Option<Transaction>,
+    pub transaction_clock: u64,
 }
 
 impl Store {
@@ -160,6 +478,24 @@ impl Store {
             primary_slotmap: SlotMap::new(),
             anymap: anymap::Map::new(),
             unseen_ids: HashSet::new(),
+            #[cfg(feature = "serde")]
+            serializers: HashMap::new(),
+            #[cfg(feature = "serde")]
+            storage: None,
+            #[cfg(feature = "serde")]
+            persisted: HashMap::new(),
+            history: None,
+            notifier: None,
+            effect_cleanups: HashMap::new(),
+            versions: HashMap::new(),
+            version_clock: 0,
+            read_trackers: vec![],
+            atom_ids: HashSet::new(),
+            kept_alive_ids: HashMap::new(),
+            taken_ids: HashSet::new(),
+            value_fns: HashMap::new(),
+            transaction: None,
+            transaction_clock: 0,
         }
     }
 
@@ -170,11 +506,34 @@ impl Store {
     }
 
     pub fn get_state_with_topo_id<T: 'static>(&mut self, current_id: topo::Id) -> Option<&T> {
-        self.unseen_ids.remove(&current_id);
-        match (
-            self.id_to_key_map.get(&current_id),
-            self.get_secondarymap::<T>(),
-        ) {
+        self.try_get_state_with_topo_id(current_id).ok()
+    }
+
+    pub fn try_get_state_with_topo_id<T: 'static>(
+        &mut self,
+        current_id: topo::Id,
+    ) -> Result<&T, StateError> {
+        self.mark_read(current_id);
+        let existing_key = *self
+            .id_to_key_map
+            .get(&current_id)
+            .ok_or(StateError::Missing)?;
+        if self.taken_ids.contains(&current_id) {
+            return Err(StateError::InUse);
+        }
+        self.get_secondarymap::<T>()
+            .and_then(|existing_secondary_map| existing_secondary_map.get(existing_key))
+            .ok_or(StateError::TypeMismatch)
+    }
+
+    // Unlike get_state_with_topo_id this does not count as the id being seen.
+    pub fn contains_state_with_topo_id<T: 'static>(&self, id: topo::Id) -> bool {
+        self.peek_state_with_topo_id::<T>(id).is_some()
+    }
+
+    // Unlike get_state_with_topo_id this does not count as the id being seen or read.
+    pub fn peek_state_with_topo_id<T: 'static>(&self, id: topo::Id) -> Option<&T> {
+        match (self.id_to_key_map.get(&id), self.get_secondarymap::<T>()) {
             (Some(existing_key), Some(existing_secondary_map)) => {
                 existing_secondary_map.get(*existing_key)
             }
@@ -182,7 +541,79 @@ impl Store {
         }
     }
 
+    // Borrows the stored value mutably rather than copying it out and back in.
+    // Changes made this way are not recorded or notified.
+    pub fn with_state_mut<T: 'static, F: FnOnce(&mut T) -> R, R>(
+        &mut self,
+        current_id: topo::Id,
+        func: F,
+    ) -> Result<R, StateError> {
+        self.unseen_ids.remove(&current_id);
+        let existing_key = *self
+            .id_to_key_map
+            .get(&current_id)
+            .ok_or(StateError::Missing)?;
+        if self.taken_ids.contains(&current_id) {
+            return Err(StateError::InUse);
+        }
+        let state = self
+            .get_mut_secondarymap::<T>()
+            .and_then(|existing_secondary_map| existing_secondary_map.get_mut(existing_key))
+            .ok_or(StateError::TypeMismatch)?;
+        let result = func(state);
+        self.bump_version(current_id);
+        Ok(result)
+    }
+
+    // Moves the value out of its slot, until return_state puts it back.
+    pub fn take_state<T: 'static>(&mut self, id: topo::Id) -> Result<T, StateError> {
+        self.mark_read(id);
+        let existing_key = *self.id_to_key_map.get(&id).ok_or(StateError::Missing)?;
+        if self.taken_ids.contains(&id) {
+            return Err(StateError::InUse);
+        }
+        let value = self
+            .get_mut_secondarymap::<T>()
+            .and_then(|existing_secondary_map| existing_secondary_map.remove(existing_key))
+            .ok_or(StateError::TypeMismatch)?;
+        self.taken_ids.insert(id);
+        Ok(value)
+    }
+
+    // The value is dropped if its slot was removed while it was taken.
+    pub fn return_state<T: 'static>(&mut self, id: topo::Id, value: T) {
+        self.taken_ids.remove(&id);
+        if let Some(existing_key) = self.id_to_key_map.get(&id).copied() {
+            if let Some(existing_secondary_map) = self.get_mut_secondarymap::<T>() {
+                existing_secondary_map.insert(existing_key, value);
+            }
+        }
+    }
+
+    // Counts as the id being seen and, while a selector is computing, as one of its inputs.
+    pub fn mark_read(&mut self, id: topo::Id) {
+        self.unseen_ids.remove(&id);
+        if let Some(reads) = self.read_trackers.last_mut() {
+            reads.insert(id);
+        }
+    }
+
+    pub fn version(&self, id: topo::Id) -> Option<u64> {
+        self.versions.get(&id).copied()
+    }
+
+    pub(crate) fn bump_version(&mut self, id: topo::Id) {
+        self.version_clock += 1;
+        self.versions.insert(id, self.version_clock);
+        #[cfg(feature = "serde")]
+        self.save_persisted(id);
+    }
+
     pub fn remove_topo_id(&mut self, id: topo::Id) {
+        self.versions.remove(&id);
+        self.kept_alive_ids.remove(&id);
+        #[cfg(feature = "serde")]
+        self.persisted.remove(&id);
         let key = self.id_to_key_map.get(&id).copied().unwrap_or_default();
         if !key.is_null() {
             self.primary_slotmap.remove(key);
@@ -201,6 +632,9 @@ impl Store {
             .unwrap_or_default();
 
         if key.is_null() {
+            if let Some(keep_alive) = topo::Env::get::<KeepAlive>() {
+                self.kept_alive_ids.insert(current_id, keep_alive.0);
+            }
             let key = self.primary_slotmap.insert(current_id);
             self.id_to_key_map.insert(current_id, key);
             if let Some(sec_map) = self.get_mut_secondarymap::<T>() {
@@ -215,6 +649,8 @@ impl Store {
             self.register_secondarymap::<T>();
             self.get_mut_secondarymap::<T>().unwrap().insert(key, data);
         }
+        // after the value is in place, as bumping the version saves persisted slots.
+        self.bump_version(current_id);
     }
 
     fn get_secondarymap<T: 'static>(&self) -> Option<&SecondaryMap<DefaultKey, T>> {
@@ -235,3 +671,147 @@ pub fn state_getter<T: 'static + Clone>() -> Arc<dyn Fn() -> Option<T>> {
     let current_id = topo::Id::current();
     Arc::new(move || get_state_with_topo_id::<T>(current_id))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::new_store;
+
+    #[test]
+    fn same_typed_use_state_calls_get_their_own_slots() {
+        init_root_context();
+        let counters = || {
+            topo::root!({
+                let (first, first_access) = use_state(|| 0);
+                let (second, second_access) = use_state(|| 10);
+                first_access.set(first + 1);
+                second_access.set(second + 2);
+                (first, second)
+            })
+        };
+
+        assert_eq!(counters(), (0, 10));
+        assert_eq!(counters(), (1, 12));
+        assert_eq!(counters(), (2, 14));
+    }
+
+    #[test]
+    fn hook_slots_rewind_each_render_after_top_level_hooks() {
+        init_root_context();
+        use_state(|| "outside of any render");
+        let counter = || {
+            topo::root!({
+                let (count, count_access) = use_state(|| 0);
+                count_access.set(count + 1);
+                count
+            })
+        };
+
+        assert_eq!(counter(), 0);
+        assert_eq!(counter(), 1);
+        assert_eq!(counter(), 2);
+    }
+
+    #[test]
+    fn clone_state_and_state_getter_read_set_state_not_hook_slots() {
+        init_root_context();
+        let (cloned, got) = topo::root!({
+            use_state(|| 1u32);
+            set_state(2u32);
+            (clone_state::<u32>(), state_getter::<u32>()())
+        });
+        assert_eq!((cloned, got), (Some(2), Some(2)));
+
+        let unset = topo::root!({
+            use_state(|| "hook".to_string());
+            clone_state::<String>()
+        });
+        assert_eq!(unset, None);
+    }
+
+    #[test]
+    fn updates_lend_the_value_out_of_its_slot() {
+        struct Handle(u32);
+
+        init_root_context();
+        let (total_access, step_access) = topo::root!((use_state(|| 1).1, use_state(|| 10).1));
+
+        total_access.update(|total| *total += step_access.hard_get());
+        assert_eq!(total_access.hard_get(), 11);
+        let own_state = total_access.try_update(|_| total_access.try_get());
+        assert_eq!(own_state, Ok(Err(StateError::InUse)));
+        let got_own_state = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            total_access.update(|_| {
+                total_access.get();
+            });
+        }));
+        assert!(got_own_state.is_err());
+        assert_eq!(total_access.hard_get(), 11);
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            total_access.update(|_| panic!("in update"));
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(total_access.hard_get(), 11);
+
+        crate::history::start_recording();
+        let handle_id = topo::Id(3);
+        let store = current_store().unwrap();
+        store
+            .borrow_mut()
+            .set_state_with_topo_id(Handle(1), handle_id);
+        update_state_with_topo_id(handle_id, |handle: &mut Handle| handle.0 += 1);
+        let handle = store
+            .borrow()
+            .peek_state_with_topo_id::<Handle>(handle_id)
+            .map(|handle| handle.0);
+        assert_eq!(handle, Some(2));
+    }
+
+    #[test]
+    fn with_lends_the_value_for_reading() {
+        init_root_context();
+        let (words_access, step_access) =
+            topo::root!((use_state(|| vec!["a".to_string()]).1, use_state(|| 1).1));
+
+        let len = words_access.with(|words| words[0].len() + step_access.hard_get());
+        assert_eq!(len, 2);
+        let own_state = words_access.with(|_| words_access.try_get());
+        assert_eq!(own_state, Err(StateError::InUse));
+        assert_eq!(words_access.hard_get(), vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn stale_state_access_errors_instead_of_panicking() {
+        let access = StateAccess::<u32>::new(topo::Id(7));
+        assert_eq!(access.try_get(), Err(StateError::NoStore));
+
+        init_root_context();
+        assert_eq!(access.try_get(), Err(StateError::Missing));
+        assert_eq!(
+            access.try_update(|value| *value += 1),
+            Err(StateError::Missing)
+        );
+        set_state_with_topo_id("seven".to_string(), access.id);
+        assert_eq!(access.try_get(), Err(StateError::TypeMismatch));
+        assert_eq!(access.try_set(7), Ok(()));
+        assert_eq!(access.try_get(), Ok(7));
+    }
+
+    #[test]
+    fn scoped_stores_keep_their_state_separate() {
+        init_root_context();
+        let app = || topo::root!(use_state(|| 0));
+        let first_store = new_store();
+        let second_store = new_store();
+
+        let (_, first_access) = with_store(&first_store, app);
+        first_access.set(1);
+        let (second, _) = with_store(&second_store, app);
+        assert_eq!(second, 0);
+        assert_eq!(with_store(&first_store, app).0, 1);
+        assert_eq!(app().0, 0);
+
+        drop(first_store);
+        assert_eq!(first_access.try_get(), Err(StateError::NoStore));
+    }
+}
@@ -0,0 +1,151 @@
+// Renders components headlessly so hooks can be tested with a plain `cargo test`.
+// Each render is a frame, as an app's view would be, inside the renderer's own Store.
+//
+// let mut renderer = TestRenderer::new(|| counter());
+// let (count, access) = renderer.render();
+// access.set(count + 1);
+// assert_eq!(renderer.rerender().0, 1);
+use crate::frame::frame;
+use crate::store::{new_store, with_store, Store};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+pub struct TestRenderer<F> {
+    component: F,
+    store: Rc<RefCell<Store>>,
+    frames: usize,
+}
+
+impl<F, V> TestRenderer<F>
+where
+    F: Fn() -> V,
+{
+    pub fn new(component: F) -> TestRenderer<F> {
+        TestRenderer {
+            component,
+            store: new_store(),
+            frames: 0,
+        }
+    }
+
+    // Renders one frame and returns what the component returned.
+    pub fn render(&mut self) -> V {
+        let component = &self.component;
+        let view = with_store(&self.store, || frame(component));
+        self.frames += 1;
+        view
+    }
+
+    // As render, for frames after the first.
+    pub fn rerender(&mut self) -> V {
+        assert!(self.frames > 0, "rerender called before render");
+        self.render()
+    }
+
+    // Renders a frame without the component, purging all of its state and running
+    // any effect cleanups. A later render mounts the component afresh.
+    pub fn unmount(&mut self) {
+        with_store(&self.store, || frame(|| ()));
+        self.frames += 1;
+    }
+
+    // The number of frames rendered so far, including unmount frames.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn store(&self) -> &Rc<RefCell<Store>> {
+        &self.store
+    }
+
+    // Runs `func` between frames with the renderer's Store in scope,
+    // e.g. to call undo() or get_state_with_topo_id.
+    pub fn in_store<R, G: FnOnce() -> R>(&self, func: G) -> R {
+        with_store(&self.store, func)
+    }
+}
+
+// Counts calls, e.g. how many times a memo recomputes or a notifier fires.
+// Clones share one count, so a clone can be moved into a component.
+#[derive(Clone, Debug, Default)]
+pub struct Counter(Rc<Cell<usize>>);
+
+impl Counter {
+    pub fn new() -> Counter {
+        Counter::default()
+    }
+
+    pub fn tick(&self) {
+        self.0.set(self.0.get() + 1);
+    }
+
+    pub fn count(&self) -> usize {
+        self.0.get()
+    }
+}
+
+// Records events in the order they happen, e.g. effects running and cleaning up.
+// Clones share one log.
+#[derive(Clone, Debug, Default)]
+pub struct Log(Rc<RefCell<Vec<String>>>);
+
+impl Log {
+    pub fn new() -> Log {
+        Log::default()
+    }
+
+    pub fn push<S: Into<String>>(&self, event: S) {
+        self.0.borrow_mut().push(event.into());
+    }
+
+    pub fn events(&self) -> Vec<String> {
+        self.0.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{do_once, use_effect, use_list, use_state, StateError};
+
+    #[test]
+    fn test_renderer_purges_unseen_state_and_unmounts() {
+        let show_list = Rc::new(Cell::new(true));
+        let log = Log::new();
+        let mut renderer = {
+            let show_list = show_list.clone();
+            let log = log.clone();
+            TestRenderer::new(move || {
+                let log = log.clone();
+                do_once(|| log.push("once"));
+                use_effect((), move || move || log.push("cleanup"));
+                let (count, count_access) = use_state(|| 0);
+                let list = if show_list.get() {
+                    Some(use_list(|| vec!["a", "b"]))
+                } else {
+                    None
+                };
+                (count, count_access, list)
+            })
+        };
+
+        let (_, count_access, list) = renderer.render();
+        count_access.set(3);
+        list.unwrap().1.push("c");
+        let (count, _, list) = renderer.rerender();
+        assert_eq!(count, 3);
+        assert_eq!(list.unwrap().0.items().count(), 3);
+
+        show_list.set(false);
+        renderer.rerender();
+        show_list.set(true);
+        let (_, _, list) = renderer.rerender();
+        assert_eq!(list.unwrap().0.items().count(), 2);
+        assert_eq!(log.events(), vec!["once"]);
+
+        renderer.unmount();
+        assert_eq!(count_access.try_get(), Err(StateError::Missing));
+        assert_eq!(renderer.render().0, 0);
+        assert_eq!(log.events(), vec!["once", "cleanup", "once"]);
+    }
+}
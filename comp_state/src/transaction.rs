@@ -0,0 +1,224 @@
+// Groups a number of state changes so that they apply as one.
+//
+// Each changed id is notified once, when the transaction ends, so a handler that makes
+// several changes causes a single redraw rather than one per intermediate state.
+// Changes are also undone and redone together by history.
+//
+// If the transaction panics, or try_transaction returns an Err, every change made through
+// set_state_with_topo_id, update_state_with_topo_id or a StateAccess is rolled back and
+// nothing is notified. Changes made through a StateRef cannot be rolled back as the
+// previous value would need to be cloned.
+//
+// transaction(|| {
+//     list_control.replace_by_key(key, item);
+//     list_control.select_only_by_key(key);
+//     form_access.set(Form::default());
+// });
+use crate::notify::notify_state_changed;
+use crate::store::{current_store, Store};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+type Rollback = Box<dyn FnOnce(&mut Store)>;
+
+pub struct Transaction {
+    pub number: u64,
+    saved_ids: HashSet<topo::Id>,
+    rollbacks: Vec<Rollback>,
+    changed_ids: Vec<topo::Id>,
+    history_position: Option<usize>,
+}
+
+impl fmt::Debug for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Transaction")
+            .field("number", &self.number)
+            .field("changed_ids", &self.changed_ids)
+            .finish()
+    }
+}
+
+impl Store {
+    pub fn begin_transaction(&mut self) {
+        self.transaction_clock += 1;
+        self.transaction = Some(Transaction {
+            number: self.transaction_clock,
+            saved_ids: HashSet::new(),
+            rollbacks: vec![],
+            changed_ids: vec![],
+            history_position: self.history_position(),
+        });
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    // Keeps the value `id` had before the transaction, the first time it is changed.
+    // A value lent out by an update cannot be changed, so there is nothing to keep.
+    pub fn save_for_rollback<T: 'static>(&mut self, id: topo::Id) {
+        if self.taken_ids.contains(&id) {
+            return;
+        }
+        let first_change = match self.transaction.as_mut() {
+            Some(transaction) => transaction.saved_ids.insert(id),
+            None => false,
+        };
+        if !first_change {
+            return;
+        }
+        let rollback: Rollback = if !self.contains_state_with_topo_id::<T>(id) {
+            Box::new(move |store: &mut Store| store.remove_topo_id(id))
+        } else {
+            match (self.copy_state::<T>(id), self.value_fns::<T>()) {
+                (Some(old), Some(value_fns)) => {
+                    Box::new(move |store: &mut Store| (value_fns.apply)(store, id, &*old))
+                }
+                _ => return,
+            }
+        };
+        self.transaction.as_mut().unwrap().rollbacks.push(rollback);
+    }
+
+    // Returns true if notifying `id` has been left until the transaction is committed.
+    pub fn defer_notify(&mut self, id: topo::Id) -> bool {
+        match self.transaction.as_mut() {
+            Some(transaction) => {
+                if !transaction.changed_ids.contains(&id) {
+                    transaction.changed_ids.push(id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Returns the ids changed by the transaction, to be notified.
+    pub fn commit_transaction(&mut self) -> Vec<topo::Id> {
+        self.transaction
+            .take()
+            .map(|transaction| transaction.changed_ids)
+            .unwrap_or_default()
+    }
+
+    pub fn rollback_transaction(&mut self) {
+        let transaction = match self.transaction.take() {
+            Some(transaction) => transaction,
+            None => return,
+        };
+        let number = transaction.number;
+        for rollback in transaction.rollbacks.into_iter().rev() {
+            rollback(self);
+        }
+        if let (Some(history), Some(position)) =
+            (self.history.as_mut(), transaction.history_position)
+        {
+            history
+                .entries
+                .retain(|entry| entry.transaction != Some(number));
+            history.position = position;
+        }
+    }
+}
+
+// Rolls back if dropped before the transaction ends, i.e. when unwinding from a panic.
+struct RollbackGuard(Option<Rc<RefCell<Store>>>);
+
+impl Drop for RollbackGuard {
+    fn drop(&mut self) {
+        if let Some(store) = self.0.take() {
+            store.borrow_mut().rollback_transaction();
+        }
+    }
+}
+
+// Rolls back if `func` returns an Err. A transaction started within another joins it,
+// so only the outermost one commits or rolls back.
+pub fn try_transaction<R, E, F: FnOnce() -> Result<R, E>>(func: F) -> Result<R, E> {
+    let store = current_store().unwrap();
+    if store.borrow().in_transaction() {
+        return func();
+    }
+    store.borrow_mut().begin_transaction();
+    let mut guard = RollbackGuard(Some(Rc::clone(&store)));
+
+    let result = func();
+
+    guard.0 = None;
+    match result {
+        Ok(value) => {
+            let changed_ids = store.borrow_mut().commit_transaction();
+            for id in changed_ids {
+                notify_state_changed(&store, id);
+            }
+            Ok(value)
+        }
+        Err(err) => {
+            store.borrow_mut().rollback_transaction();
+            Err(err)
+        }
+    }
+}
+
+pub fn transaction<R, F: FnOnce() -> R>(func: F) -> R {
+    match try_transaction::<R, (), _>(|| Ok(func())) {
+        Ok(value) => value,
+        Err(()) => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Counter;
+    use crate::{history, init_root_context, set_state_changed_notifier, use_state, StateError};
+
+    #[test]
+    fn rolling_back_leaves_values_lent_out_by_an_update_alone() {
+        init_root_context();
+        let (_, access) = topo::root!(use_state(|| 1));
+
+        access.update(|value| {
+            *value += 1;
+            let rolled_back = try_transaction(|| access.try_update(|value| *value += 10));
+            assert_eq!(rolled_back, Err(StateError::InUse));
+        });
+        assert_eq!(access.try_get(), Ok(2));
+    }
+
+    #[test]
+    fn transactions_notify_once_and_roll_back_on_error_or_panic() {
+        init_root_context();
+        history::start_recording();
+        let notified = Counter::new();
+        let counter = notified.clone();
+        set_state_changed_notifier(move |_id| counter.tick());
+        let (_, access) = topo::root!(use_state(|| 1));
+
+        transaction(|| {
+            access.set(2);
+            access.update(|value| *value += 1);
+        });
+        assert_eq!((access.hard_get(), notified.count()), (3, 1));
+
+        let aborted: Result<(), &str> = try_transaction(|| {
+            access.set(10);
+            Err("abort")
+        });
+        assert_eq!(aborted, Err("abort"));
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            transaction(|| {
+                access.set(20);
+                panic!("in transaction");
+            })
+        }));
+        assert!(panicked.is_err());
+        assert_eq!((access.hard_get(), notified.count()), (3, 1));
+
+        assert!(history::undo());
+        assert_eq!(access.hard_get(), 1);
+        assert!(!history::undo());
+    }
+}
@@ -68,12 +68,18 @@ fn parent_and_child_components_example() -> Node<Msg> {
     ]
 }
 
+#[derive(Clone)]
+struct Note {
+    title: String,
+    description: String,
+}
+
 #[topo::nested]
-fn complex_child_component_example(parent_text_access: StateAccess<String>) -> Node<Msg> {
+fn complex_child_component_example(description_access: StateAccess<String>) -> Node<Msg> {
     div![
         label!["this is a child component's input:"],
         input![input_ev("input", move |text| {
-            parent_text_access.set(text);
+            description_access.set(text);
             Msg::DoNothing
         })],
     ]
@@ -82,11 +88,20 @@ fn complex_child_component_example(parent_text_access: StateAccess<String>) -> N
 #[topo::nested]
 fn complex_parent_and_child_components_example() -> Node<Msg> {
     // by passing the accessor to child components we can access different 'components'.
+    // map focuses the accessor so the child can only change the note's description.
 
-    let (parent_text, parent_text_access) = use_state(|| "".to_string());
+    let (note, note_access) = use_state(|| Note {
+        title: "Note".to_string(),
+        description: "".to_string(),
+    });
+    let description_access =
+        note_access.map(|note| &note.description, |note| &mut note.description);
     div![
-        h3![format!("text from child component: {}", parent_text)],
-        complex_child_component_example!(parent_text_access)
+        h3![format!(
+            "{} text from child component: {}",
+            note.title, note.description
+        )],
+        complex_child_component_example!(description_access)
     ]
 }
 
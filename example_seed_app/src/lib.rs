@@ -37,7 +37,7 @@ fn update(msg: Msg, model: &mut Model, _: &mut impl Orders<Msg>) {
 
 // View
 fn view(model: &Model) -> impl View<Msg> {
-    topo::root!({
+    comp_state::frame(|| {
         div![
             hook_playground::view(),
             button![
@@ -1,5 +1,8 @@
 pub mod event_helpers;
 pub mod form_state;
 pub mod graphql_list;
+#[cfg(feature = "persist")]
+pub mod storage;
 pub mod two_way;
 pub mod use_fetch_helper;
+pub mod use_resource;
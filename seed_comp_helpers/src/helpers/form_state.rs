@@ -2,7 +2,7 @@
 // only password and text inputs implemented
 // this can be easily extendable to other other form element types
 
-use comp_state::{state_getter, use_state};
+use comp_state::{use_state, StateAccess};
 use fancy_regex::Regex;
 use seed::prelude::*;
 use std::sync::Arc;
@@ -34,12 +34,13 @@ where
     Ms: Default,
 {
     pub fn build(&self) -> (FormState, FormControl<Ms>) {
-        let (form_state, _set_state) = use_state(FormState::default);
+        let (form_state, form_state_access) = use_state(FormState::default);
         (
             form_state,
             FormControl {
                 _phantom: PhantomData,
                 on_blur_closure: self.on_blur_closure.clone(),
+                form_state_access,
             },
         )
     }
@@ -110,10 +111,12 @@ impl InputState {
 // PhantomData needed as Ms is the Msg type that is application specific and used in specific seed object types
 // the on_blur closure accepts a closure to be run whenever any element loses focus
 // this runs after any specific element validation etc.
+// form_state_access is the single hook slot the whole form reads and writes.
 use std::marker::PhantomData;
 pub struct FormControl<Ms> {
     _phantom: PhantomData<Ms>,
     on_blur_closure: Option<Arc<dyn Fn(FormState) -> ()>>,
+    form_state_access: StateAccess<FormState>,
 }
 
 impl<Ms> FormControl<Ms>
@@ -122,15 +125,25 @@ where
 {
     // Constructor for a text InputBuilder
     pub fn text<T: Into<String>>(&self, name: T) -> InputBuilder<Ms> {
-        InputBuilder::new(name, InputType::Text, self.on_blur_closure.clone())
+        InputBuilder::new(
+            name,
+            InputType::Text,
+            self.on_blur_closure.clone(),
+            self.form_state_access.clone(),
+        )
     }
     // Constructor for a password InputBuilder
     pub fn password<T: Into<String>>(&self, name: T) -> InputBuilder<Ms> {
-        InputBuilder::new(name, InputType::Password, self.on_blur_closure.clone())
+        InputBuilder::new(
+            name,
+            InputType::Password,
+            self.on_blur_closure.clone(),
+            self.form_state_access.clone(),
+        )
     }
     pub fn input_errors_for<T: Into<String>>(&self, name: T) -> Vec<Node<Ms>> {
         let name = name.into();
-        let (form_state, _set_state) = use_state(FormState::default);
+        let form_state = self.form_state_access.get().unwrap_or_default();
         if let Some(input) = form_state.values.iter().find(|inp| inp.name == name) {
             input.errors.iter().map(|err| span![err]).collect::<_>()
         } else {
@@ -153,6 +166,7 @@ pub struct InputBuilder<Ms> {
     form_on_blur_closure: Option<Arc<dyn Fn(FormState) -> ()>>,
     validate_closures: Vec<ValidationClosure>,
     validate_on: InputBlurBothEnum,
+    form_state_access: StateAccess<FormState>,
     _phantom: PhantomData<Ms>,
 }
 
@@ -164,6 +178,7 @@ where
         name: T,
         input_type: InputType,
         form_on_blur_closure: Option<Arc<dyn Fn(FormState) -> ()>>,
+        form_state_access: StateAccess<FormState>,
     ) -> InputBuilder<Ms> {
         InputBuilder {
             _phantom: PhantomData,
@@ -173,6 +188,7 @@ where
             form_on_blur_closure: form_on_blur_closure.clone(),
             validate_closures: vec![],
             validate_on: InputBlurBothEnum::Both,
+            form_state_access,
             input_type,
         }
     }
@@ -246,7 +262,8 @@ where
     fn password_attrs<T: Into<String>>(&self, name: T) -> seed::dom_types::Attrs {
         let name = name.into();
         // state and access to the form_state, form_state needs to be mutated with new InputState if one does not already exist
-        let (mut form_state, form_state_access) = use_state(FormState::default);
+        let form_state_access = &self.form_state_access;
+        let mut form_state = form_state_access.get().unwrap_or_default();
 
         let text_input_value =
             if let Some(input) = form_state.values.iter().find(|input| input.name == name) {
@@ -262,7 +279,8 @@ where
     fn text_attrs<T: Into<String>>(&self, name: T) -> seed::dom_types::Attrs {
         let name = name.into();
         // state and access to the form_state, form_state needs to be mutated with new InputState if one does not already exist
-        let (mut form_state, form_state_access) = use_state(FormState::default);
+        let form_state_access = &self.form_state_access;
+        let mut form_state = form_state_access.get().unwrap_or_default();
 
         let text_input_value =
             if let Some(input) = form_state.values.iter().find(|input| input.name == name) {
@@ -282,7 +300,7 @@ where
     // Helper events
 
     fn clear_errors_blur_event(&self, name: String) -> seed::events::Listener<Ms> {
-        let (_form_state, form_state_access) = use_state(FormState::default);
+        let form_state_access = self.form_state_access.clone();
         input_ev("blur", move |_text| {
             if let Some(mut form_state) = form_state_access.get() {
                 if let Some(input) = form_state
@@ -298,7 +316,7 @@ where
         })
     }
     fn clear_errors_input_event(&self, name: String) -> seed::events::Listener<Ms> {
-        let (_form_state, form_state_access) = use_state(FormState::default);
+        let form_state_access = self.form_state_access.clone();
         input_ev("input", move |_text| {
             if let Some(mut form_state) = form_state_access.get() {
                 if let Some(input) = form_state
@@ -315,7 +333,7 @@ where
     }
 
     fn update_value_input_event(&self, name: String) -> seed::events::Listener<Ms> {
-        let (_form_state, form_state_access) = use_state(FormState::default);
+        let form_state_access = self.form_state_access.clone();
         input_ev("input", move |text| {
             if let Some(mut form_state) = form_state_access.get() {
                 if let Some(input) = form_state
@@ -353,10 +371,10 @@ where
 
     fn form_on_blur_event(&self) -> Option<seed::events::Listener<Ms>> {
         let closure = self.form_on_blur_closure.clone();
-        let form_state_getter = state_getter::<FormState>();
+        let form_state_access = self.form_state_access.clone();
         if closure.is_some() {
             Some(input_ev("blur", move |_text| {
-                if let (Some(callback), Some(form_state)) = (closure, form_state_getter()) {
+                if let (Some(callback), Some(form_state)) = (closure, form_state_access.get()) {
                     callback(form_state);
                 };
                 Ms::default()
@@ -373,7 +391,7 @@ where
     ) -> Option<seed::events::Listener<Ms>> {
         let closures = self.validate_closures.clone();
 
-        let (_form_state, form_state_access) = use_state(FormState::default);
+        let form_state_access = self.form_state_access.clone();
         if !closures.is_empty() {
             Some(input_ev(event_type, move |text| {
                 if let Some(mut form_state) = form_state_access.get() {
@@ -0,0 +1,71 @@
+// Browser StorageBackends for comp_state::use_persisted_state, behind the persist feature.
+//
+// comp_state::set_storage_backend(LocalStorage);
+//
+// Where the browser refuses access to storage, e.g. in some private browsing modes,
+// nothing is loaded and saves are dropped so state behaves as if it were not persisted.
+use comp_state::StorageBackend;
+use seed::prelude::*;
+use web_sys::Storage;
+
+// Kept until cleared by the user or the app.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalStorage;
+
+// Kept for as long as the tab is open, including across reloads.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SessionStorage;
+
+impl StorageBackend for LocalStorage {
+    fn load(&self, key: &str) -> Option<String> {
+        load(seed::window().local_storage(), key)
+    }
+
+    fn save(&self, key: &str, value: &str) {
+        save(seed::window().local_storage(), key, value)
+    }
+
+    fn remove(&self, key: &str) {
+        remove(seed::window().local_storage(), key)
+    }
+}
+
+impl StorageBackend for SessionStorage {
+    fn load(&self, key: &str) -> Option<String> {
+        load(seed::window().session_storage(), key)
+    }
+
+    fn save(&self, key: &str, value: &str) {
+        save(seed::window().session_storage(), key, value)
+    }
+
+    fn remove(&self, key: &str) {
+        remove(seed::window().session_storage(), key)
+    }
+}
+
+fn available(storage: Result<Option<Storage>, JsValue>) -> Option<Storage> {
+    match storage {
+        Ok(storage) => storage,
+        Err(_) => None,
+    }
+}
+
+fn load(storage: Result<Option<Storage>, JsValue>, key: &str) -> Option<String> {
+    available(storage)?.get_item(key).ok()?
+}
+
+fn save(storage: Result<Option<Storage>, JsValue>, key: &str, value: &str) {
+    if let Some(storage) = available(storage) {
+        // fails once the storage quota is used up.
+        if storage.set_item(key, value).is_err() {
+            log!(format!("could not save '{}' to storage", key));
+        }
+    }
+}
+
+fn remove(storage: Result<Option<Storage>, JsValue>, key: &str) {
+    if let Some(storage) = available(storage) {
+        let _ = storage.remove_item(key);
+    }
+}
@@ -61,7 +61,13 @@ pub fn use_two_way<
             (context_access, func2(shared_access.clone()))
         });
 
-        shared_access.set((first_component_access, second_component_access));
+        // only set when first rendered, an unconditional set would be a state change every render.
+        let shared_ids = shared_access
+            .get()
+            .map(|(first, second)| (first.id, second.id));
+        if shared_ids != Some((first_component_access.id, second_component_access.id)) {
+            shared_access.set((first_component_access, second_component_access));
+        }
         (view1, view2)
     })
 }
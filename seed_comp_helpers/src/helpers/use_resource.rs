@@ -0,0 +1,139 @@
+// Runs a future whenever `deps` change and keeps its outcome in the component's slot.
+// Unlike use_fetch this works with any future, not just http requests.
+//
+// let user = use_resource(user_id, |user_id| fetch_user(*user_id));
+// match user {
+//     Resource::Loading => div!["loading..."],
+//     Resource::Ready(user) => user_view(user),
+//     Resource::Error(err) => div![format!("failed: {:?}", err)],
+// }
+//
+// A run whose deps have since changed, or whose component is no longer rendered, is
+// discarded when it completes so it can never overwrite a later result.
+use comp_state::{current_store, use_effect, use_state};
+use futures::Future;
+use std::cell::Cell;
+use std::rc::Rc;
+use wasm_bindgen_futures::spawn_local;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resource<T, E> {
+    Loading,
+    Ready(T),
+    Error(E),
+}
+
+type Spawned = Box<dyn Future<Item = (), Error = ()>>;
+
+pub fn use_resource<D, F, Fut, T, E>(deps: D, async_fn: F) -> Resource<T, E>
+where
+    D: 'static + Clone + PartialEq,
+    F: FnOnce(&D) -> Fut,
+    Fut: Future<Item = T, Error = E> + 'static,
+    T: 'static + Clone,
+    E: 'static + Clone,
+{
+    use_resource_spawned(deps, async_fn, spawn_local)
+}
+
+// As use_resource with the runs handed to `spawn`, so tests can drive them without a browser.
+fn use_resource_spawned<D, F, Fut, T, E, S>(deps: D, async_fn: F, spawn: S) -> Resource<T, E>
+where
+    D: 'static + Clone + PartialEq,
+    F: FnOnce(&D) -> Fut,
+    Fut: Future<Item = T, Error = E> + 'static,
+    T: 'static + Clone,
+    E: 'static + Clone,
+    S: FnOnce(Spawned),
+{
+    let (mut resource, resource_access) = use_state(|| Resource::<T, E>::Loading);
+    let run_deps = deps.clone();
+
+    use_effect(deps, || {
+        match resource {
+            Resource::Loading => {}
+            // part of this render rather than a change, so it is not notified.
+            _ => {
+                resource = Resource::Loading;
+                current_store()
+                    .unwrap()
+                    .borrow_mut()
+                    .set_state_with_topo_id(Resource::<T, E>::Loading, resource_access.id);
+            }
+        }
+        let outdated = Rc::new(Cell::new(false));
+        let future = async_fn(&run_deps).then({
+            let outdated = outdated.clone();
+            move |result| -> Result<(), ()> {
+                if !outdated.get() {
+                    // errors only if the store has gone, in which case there is nothing to show.
+                    let _ = resource_access.try_set(match result {
+                        Ok(value) => Resource::Ready(value),
+                        Err(err) => Resource::Error(err),
+                    });
+                }
+                Ok(())
+            }
+        });
+        spawn(Box::new(future));
+        move || outdated.set(true)
+    });
+
+    resource
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use comp_state::set_state_changed_notifier;
+    use comp_state::testing::{Counter, TestRenderer};
+    use futures::future;
+    use std::cell::RefCell;
+
+    #[test]
+    fn runs_settle_the_resource_unless_outdated() {
+        let user_id = Rc::new(Cell::new(1));
+        let spawned = Rc::new(RefCell::new(Vec::<Spawned>::new()));
+        let mut renderer = TestRenderer::new({
+            let (user_id, spawned) = (user_id.clone(), spawned.clone());
+            move || {
+                let spawned = spawned.clone();
+                use_resource_spawned(
+                    user_id.get(),
+                    |user_id| -> Box<dyn Future<Item = u32, Error = String>> {
+                        if *user_id > 0 {
+                            Box::new(future::ok(*user_id * 10))
+                        } else {
+                            Box::new(future::err("no user".to_string()))
+                        }
+                    },
+                    move |run| spawned.borrow_mut().push(run),
+                )
+            }
+        });
+        let notified = Counter::new();
+        renderer.in_store({
+            let notified = notified.clone();
+            move || set_state_changed_notifier(move |_id| notified.tick())
+        });
+        let settle = |idx: usize| spawned.borrow_mut().remove(idx).wait().unwrap();
+
+        assert_eq!(renderer.render(), Resource::Loading);
+        settle(0);
+        assert_eq!(renderer.rerender(), Resource::Ready(10));
+
+        user_id.set(0);
+        assert_eq!(renderer.rerender(), Resource::Loading);
+        assert_eq!(notified.count(), 1);
+        settle(0);
+        assert_eq!(renderer.rerender(), Resource::Error("no user".to_string()));
+
+        user_id.set(2);
+        renderer.rerender();
+        user_id.set(3);
+        assert_eq!(renderer.rerender(), Resource::Loading);
+        settle(1);
+        settle(0);
+        assert_eq!(renderer.rerender(), Resource::Ready(30));
+    }
+}
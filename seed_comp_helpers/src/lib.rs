@@ -5,16 +5,46 @@ pub mod helpers;
 pub use helpers::event_helpers::{on_click, on_input};
 pub use helpers::form_state;
 pub use helpers::graphql_list;
+#[cfg(feature = "persist")]
+pub use helpers::storage;
 pub use helpers::two_way;
 pub use helpers::use_fetch_helper;
+pub use helpers::use_resource::{use_resource, Resource};
 use seed::prelude::*;
+use std::cell::Cell;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
 
-pub fn init<Ms: 'static, Mdl: 'static, O: Orders<Ms>>(orders: &mut O) {
+// Also registers a state changed notifier so that state set from event handlers,
+// timers, fetch callbacks or mailboxes is always redrawn.
+pub fn init<Ms: Default + 'static, Mdl: 'static, O: Orders<Ms>>(orders: &mut O) {
     comp_state::init_root_context();
     if topo::Env::get::<seed::App<Ms, Mdl, Node<Ms>>>().is_none() {
         topo::Env::add(orders.clone_app());
     }
     comp_state::init_root_context();
+    comp_state::set_state_changed_notifier(|_id| schedule_frame_update::<Ms, Mdl>());
+}
+
+thread_local! {
+    static FRAME_UPDATE_SCHEDULED: Cell<bool> = Cell::new(false);
+}
+
+// Coalesces any number of calls within a frame into a single app.update(Ms::default())
+// on the next animation frame.
+pub fn schedule_frame_update<Ms: Default + 'static, Mdl: 'static>() {
+    if FRAME_UPDATE_SCHEDULED.with(|scheduled| scheduled.replace(true)) {
+        return;
+    }
+    let callback = Closure::once_into_js(move || {
+        FRAME_UPDATE_SCHEDULED.with(|scheduled| scheduled.set(false));
+        if let Some(app) = topo::Env::get::<seed::App<Ms, Mdl, Node<Ms>>>() {
+            app.update(Ms::default());
+        }
+    });
+    seed::window()
+        .request_animation_frame(callback.unchecked_ref())
+        .expect("Problem requesting animation frame");
 }
 
 pub fn schedule_update<Ms: Clone + 'static, Mdl: 'static>(msg: Ms) {
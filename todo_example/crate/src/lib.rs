@@ -1,10 +1,8 @@
 #![allow(clippy::used_underscore_binding)]
 #![allow(clippy::non_ascii_literal)]
 #![allow(clippy::enum_glob_use)]
-use comp_state::Store;
 use seed_comp_helpers::use_fetch_helper;
 use seed_comp_helpers::use_fetch_helper::UseFetchMsgTrait;
-use std::cell::RefCell;
 mod generated;
 mod todo;
 
@@ -96,7 +94,7 @@ pub fn view(_model: &Model) -> impl View<Msg> {
     // One advantage of state stored in components is that
     // One can simply repeatedly 'render' the view
     // and each view will have its own state that just "works"
-    topo::root!(
+    comp_state::frame(|| {
         div![
             h1!["Household Chores"],
             todo::masterview(&[
@@ -119,8 +117,8 @@ pub fn view(_model: &Model) -> impl View<Msg> {
                 "Watch Patriot",
                 "Watch Mr Robot"
             ]),
-        ] // env! {RefCell<Store> => ),}
-    )
+        ]
+    })
 }
 
 pub fn image_src(image: &str) -> String {
@@ -138,9 +136,7 @@ pub fn asset_path(asset: &str) -> String {
 #[wasm_bindgen(start)]
 pub fn run() {
     log!("Starting app...");
-    if topo::Env::get::<RefCell<Store>>().is_none() {
-        topo::Env::add(RefCell::new(Store::default()));
-    }
+    comp_state::init_root_context();
     App::build(init, update, view)
         .routes(routes)
         .build_and_start();
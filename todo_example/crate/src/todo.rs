@@ -2,9 +2,9 @@
 use super::{Model, Msg};
 use crate::generated::css_classes::C;
 use comp_state::do_once;
-use comp_state::{set_state, use_state};
-use comp_state::{use_list, ListControl};
-use comp_state::{use_memo, watch};
+use comp_state::{set_state, use_state, StateAccess};
+use comp_state::{use_list, List, ListControl, ListKey};
+use comp_state::use_memo_deps;
 use enclose::enclose as e;
 use seed::dom_types::UpdateEl;
 use seed::{prelude::*, *};
@@ -12,6 +12,7 @@ use seed_comp_helpers::use_fetch_helper::use_fetch;
 use seed_comp_helpers::use_fetch_helper::{UseFetchStatus, UseFetchStatusTrait};
 
 use serde::Deserialize;
+use std::collections::HashMap;
 #[derive(Clone, Debug, PartialEq)]
 struct Item {
     description: String,
@@ -33,6 +34,23 @@ enum Status {
     Completed,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    All,
+    Active,
+    Completed,
+}
+
+impl Tab {
+    fn shows(self, item: &Item) -> bool {
+        match self {
+            Tab::All => true,
+            Tab::Active => item.status == Status::Todo,
+            Tab::Completed => item.status == Status::Completed,
+        }
+    }
+}
+
 #[derive(Clone, Default)]
 struct ItemState {
     adding: String,
@@ -46,55 +64,92 @@ pub fn masterview(tasks: &[&str]) -> Node<Msg> {
     // gives the block inside its only execution context.
     topo::call!({
         // list control lets you interact with items in the list
-        let (_list, list_control) = use_list(|| tasks);
+        let (list, list_control) = use_list(|| tasks);
         // within this component allow only global access to the list control
         set_state(list_control);
-        div![render_list(), list_controls(),]
+        div![render_list(&list), list_controls(),]
     })
 }
 
-fn render_list() -> Node<Msg> {
+fn render_list(list: &List<Item>) -> Node<Msg> {
     let list_control = comp_state::clone_state::<ListControl<Item>>().unwrap();
-    let list = list_control.get_list();
-    div![ul![
-        class![
-            C.p_3,
-            C.bg_gray_9,
-            C.border_solid,
-            C.border_gray_4,
-            C.m_4,
-            C.rounded,
-        ],
-        list.items()
-            .enumerate()
-            .map(|(idx, item)| {
-                li![
-                    class![
-                        C.rounded,
-                        C.bg_gray_1,
-                        C.p_3,
-                        C.border_solid,
-                        C.border_gray_4,
-                        C.m_4,
-                        C.shadow,
-                        C.flex,
-                        C.flex_row,
-                    ],
-                    if item.status == Status::Completed {
-                        completed_item_view(idx, item)
-                    } else {
-                        item_view(idx, item)
-                    },
-                    move_up_button(idx),
-                    move_down_button(idx),
-                ]
-            })
-            .collect::<Vec<Node<Msg>>>()
-    ]]
+    let (tab, tab_access) = use_state(|| Tab::All);
+    // the view leaves the list's order alone, so positions in the list are found by key.
+    let view = list.view().filter(|item| tab.shows(item));
+    let list_idxs = list
+        .keyed_items()
+        .enumerate()
+        .map(|(idx, (key, _))| (key, idx))
+        .collect::<HashMap<ListKey, usize>>();
+    let list_len = list_idxs.len();
+    div![
+        tabs(tab, tab_access),
+        ul![
+            class![
+                C.p_3,
+                C.bg_gray_9,
+                C.border_solid,
+                C.border_gray_4,
+                C.m_4,
+                C.rounded,
+            ],
+            // keyed so that each item's component state moves with it when the list is reordered.
+            // clone_state only finds the list control in this component, so it is passed on.
+            view.iter()
+                .map(|(key, item)| comp_state::keyed!(key, {
+                    let idx = list_idxs[&key];
+                    li![
+                        class![
+                            C.rounded,
+                            C.bg_gray_1,
+                            C.p_3,
+                            C.border_solid,
+                            C.border_gray_4,
+                            C.m_4,
+                            C.shadow,
+                            C.flex,
+                            C.flex_row,
+                        ],
+                        if item.status == Status::Completed {
+                            completed_item_view(&list_control, idx, key, item)
+                        } else {
+                            item_view(&list_control, idx, key, item)
+                        },
+                        move_up_button(&list_control, idx),
+                        move_down_button(&list_control, idx, list_len),
+                    ]
+                }))
+                .collect::<Vec<Node<Msg>>>()
+        ]
+    ]
 }
 
-fn move_up_button(idx: usize) -> Node<Msg> {
-    let list_control = comp_state::clone_state::<ListControl<Item>>().unwrap();
+fn tabs(current: Tab, tab_access: StateAccess<Tab>) -> Node<Msg> {
+    div![[(Tab::All, "All"), (Tab::Active, "Active"), (Tab::Completed, "Completed")]
+        .iter()
+        .map(|(tab, label)| {
+            let tab = *tab;
+            button![
+                if tab == current {
+                    class![C.p_2, C.m_2, C.bg_gray_5]
+                } else {
+                    class![C.p_2, C.m_2]
+                },
+                *label,
+                mouse_ev(
+                    Ev::Click,
+                    e!((tab_access) move |_| {
+                        tab_access.set(tab);
+                        Msg::DoNothing
+                    })
+                )
+            ]
+        })
+        .collect::<Vec<Node<Msg>>>()]
+}
+
+fn move_up_button(list_control: &ListControl<Item>, idx: usize) -> Node<Msg> {
+    let list_control = list_control.clone();
     // let list = list_control.get_list();
     if idx != 0 {
         i![
@@ -109,10 +164,9 @@ fn move_up_button(idx: usize) -> Node<Msg> {
     }
 }
 
-fn move_down_button(idx: usize) -> Node<Msg> {
-    let list_control = comp_state::clone_state::<ListControl<Item>>().unwrap();
-    let list = list_control.get_list();
-    if idx != list.items().count() - 1 {
+fn move_down_button(list_control: &ListControl<Item>, idx: usize, len: usize) -> Node<Msg> {
+    let list_control = list_control.clone();
+    if idx != len - 1 {
         i![
             class!["fas fa-arrow-down", C.cursor_pointer, C.flex_none, C.mr_4],
             mouse_ev("click", move |_| {
@@ -126,26 +180,26 @@ fn move_down_button(idx: usize) -> Node<Msg> {
 }
 
 // this function shows an example of using memoization on Node<Msgs>
-fn completed_item_view(idx: usize, item: &Item) -> Node<Msg> {
-    let list_control = comp_state::clone_state::<ListControl<Item>>().unwrap();
-    let (item, idx) = (watch(item), watch(&idx));
-    let (nodes, _memo_ctl) = use_memo(item.changed || idx.changed, || {
-        // If using use_memo you need to ensure anything that can change is updated via a function
-        // and not just used in the closure becuase it gets captured on first run.
-        let list = list_control.get_list();
-        let (item, idx) = (item.hard_get(), idx.hard_get());
-        //
-        //
-
+fn completed_item_view(
+    list_control: &ListControl<Item>,
+    idx: usize,
+    key: ListKey,
+    item: &Item,
+) -> Node<Msg> {
+    let list_control = list_control.clone();
+    // recomputed only when the item or its position changes, the closure is given the
+    // current values rather than capturing them.
+    use_memo_deps((item.clone(), idx), |(item, idx)| {
+        let idx = *idx;
         span![
             class![C.flex_1],
             i![class!["far fa-check-circle", C.cursor_pointer, C.mr_4], {
                 mouse_ev(
                     "click",
-                    e!((list_control) move |_| {
-                        let mut item = list.items().nth(idx).unwrap().clone();
+                    e!((list_control, item) move |_| {
+                        let mut item = item.clone();
                         item.status = Status::Todo;
-                        list_control.replace(idx, item);
+                        list_control.replace_by_key(key, item);
                         Msg::DoNothing
                     }),
                 )
@@ -155,12 +209,10 @@ fn completed_item_view(idx: usize, item: &Item) -> Node<Msg> {
                 format!("{} ) {}", idx + 1, item.description)
             ],
         ]
-    });
-    nodes
+    })
 }
-fn item_view(idx: usize, item: &Item) -> Node<Msg> {
-    let list_control = comp_state::clone_state::<ListControl<Item>>().unwrap();
-    let list = list_control.get_list();
+fn item_view(list_control: &ListControl<Item>, idx: usize, key: ListKey, item: &Item) -> Node<Msg> {
+    let list_control = list_control.clone();
     span![
         class![C.flex_1],
         i![
@@ -173,9 +225,11 @@ fn item_view(idx: usize, item: &Item) -> Node<Msg> {
             ],
             {
                 mouse_ev("click", move |_| {
-                    let mut item = list.items().nth(idx).unwrap().clone();
-                    item.status = Status::Completed;
-                    list_control.replace(idx, item);
+                    // the key still finds this item if others have moved since the render
+                    if let Some(mut item) = list_control.get_by_key(key) {
+                        item.status = Status::Completed;
+                        list_control.replace_by_key(key, item);
+                    }
                     Msg::DoNothing
                 })
             },